@@ -0,0 +1,296 @@
+//! Derive macro for [`ashpan::Destroyable`](https://docs.rs/ashpan/latest/ashpan/trait.Destroyable.html).
+//!
+//! This crate is an implementation detail of [`ashpan`](https://docs.rs/ashpan/); enable its
+//! `derive` feature rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, Index, LitInt, Member,
+    Type,
+};
+
+/// Derives [`Destroyable`](https://docs.rs/ashpan/latest/ashpan/trait.Destroyable.html) for a
+/// struct by destroying each of its fields.
+///
+/// By default, fields are destroyed in reverse declaration order, which is usually correct
+/// because later fields tend to depend on earlier ones:
+///
+/// ```
+/// use ash::vk;
+/// use ashpan::Destroyable;
+///
+/// #[derive(Destroyable)]
+/// struct Resources {
+///     render_pass: vk::RenderPass,
+///     pipeline_layout: vk::PipelineLayout,
+///     // Destroyed first
+///     pipeline: vk::Pipeline,
+/// }
+/// ```
+///
+/// The following attributes are supported:
+///
+/// * `#[destroyable(destroyer = Type)]` on the struct sets
+///   [`Destroyer`](https://docs.rs/ashpan/latest/ashpan/trait.Destroyable.html#associatedtype.Destroyer).
+///   Each field is destroyed with the destroyer that `Type` provides via
+///   [`DestroyerSource`](https://docs.rs/ashpan/latest/ashpan/trait.DestroyerSource.html), so
+///   fields with different destroyers can be mixed by using e.g. `DeviceContext`. Fields whose
+///   destroyer isn't available are left alone and reported like
+///   [`DeviceContext::destroy`](https://docs.rs/ashpan/latest/ashpan/struct.DeviceContext.html#method.destroy)
+///   does: debug builds panic, and the `leak-tracking` feature flags their handles. If omitted,
+///   the `Destroyer` of the first field to be destroyed is used.
+/// * `#[destroyable(skip)]` on a field leaves it alone during destruction.
+/// * `#[destroyable(order = N)]` on a field destroys it before all fields without an explicit
+///   order. Fields with explicit orders are destroyed in increasing `N`.
+///
+/// ```
+/// use ash::vk;
+/// use ashpan::Destroyable;
+///
+/// #[derive(Destroyable)]
+/// #[destroyable(destroyer = ash::Device)]
+/// struct Frame {
+///     #[destroyable(skip)]
+///     index: usize,
+///     // Destroyed third
+///     command_pool: vk::CommandPool,
+///     // Destroyed second
+///     fence: vk::Fence,
+///     // Destroyed first
+///     #[destroyable(order = 0)]
+///     framebuffer: vk::Framebuffer,
+/// }
+/// ```
+///
+/// Every destroyed field's `Destroyer` is checked against the struct's at compile time:
+///
+/// ```compile_fail
+/// use ash::vk;
+/// use ashpan::Destroyable;
+///
+/// #[derive(Destroyable)]
+/// struct Mismatched {
+///     buffer: vk::Buffer,
+///     swapchain: vk::SwapchainKHR, // Destroyed by khr::Swapchain, not ash::Device
+/// }
+/// ```
+#[proc_macro_derive(Destroyable, attributes(destroyable))]
+pub fn derive_destroyable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct DestroyedField {
+    member: Member,
+    ty: Type,
+    order: Option<(u64, Span)>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "Destroyable can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut destroyer = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("destroyable") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("destroyer") {
+                destroyer = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `destroyer = Type`"))
+            }
+        })?;
+    }
+
+    let mut destroyed_fields = destroyed_fields(fields)?;
+    order_fields(&mut destroyed_fields)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+
+    // Field Destroyers are checked against the struct's with errors pointing at the field: via
+    // where clauses if the Destroyer is explicit, otherwise by the calls to destroy_with(). An
+    // explicit Destroyer only needs to be a DestroyerSource of each field's Destroyer.
+    let explicit = destroyer.is_some();
+    let destroyer = match (destroyer, destroyed_fields.first()) {
+        (Some(destroyer), _) => {
+            for DestroyedField { ty, .. } in &destroyed_fields {
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote_spanned! {ty.span()=>
+                        #ty: ::ashpan::Destroyable
+                    });
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote_spanned! {ty.span()=>
+                        #destroyer: ::ashpan::DestroyerSource<
+                            <#ty as ::ashpan::Destroyable>::Destroyer,
+                        >
+                    });
+            }
+            destroyer.into_token_stream()
+        }
+        (None, Some(DestroyedField { ty, .. })) => {
+            for DestroyedField { ty, .. } in &destroyed_fields {
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote_spanned! {ty.span()=>
+                        #ty: ::ashpan::Destroyable
+                    });
+            }
+            quote!(<#ty as ::ashpan::Destroyable>::Destroyer)
+        }
+        (None, None) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`#[destroyable(destroyer = Type)]` is required when no fields are destroyed",
+            ))
+        }
+    };
+
+    // Fields whose destroyer isn't available from an explicit Destroyer are left alone and
+    // reported as leaked, since destroy_with() usually runs while a guard is dropped.
+    let destroy_calls = destroyed_fields
+        .iter()
+        .map(|DestroyedField { member, ty, .. }| {
+            if explicit {
+                quote_spanned! {ty.span()=>
+                    match ::ashpan::DestroyerSource::destroyer(destroyer) {
+                        ::core::option::Option::Some(field_destroyer) => {
+                            ::ashpan::Destroyable::destroy_with(
+                                &mut self.#member,
+                                field_destroyer,
+                                allocation_callbacks,
+                            );
+                        }
+                        ::core::option::Option::None => {
+                            ::ashpan::__private::destroyer_unavailable(&self.#member);
+                        }
+                    }
+                }
+            } else {
+                quote_spanned! {ty.span()=>
+                    ::ashpan::Destroyable::destroy_with(
+                        &mut self.#member,
+                        destroyer,
+                        allocation_callbacks,
+                    );
+                }
+            }
+        });
+
+    let visit_calls = destroyed_fields
+        .iter()
+        .map(|DestroyedField { member, ty, .. }| {
+            quote_spanned! {ty.span()=>
+                ::ashpan::Destroyable::visit_handles(&self.#member, visitor);
+            }
+        });
+
+    Ok(quote! {
+        impl #impl_generics ::ashpan::Destroyable for #name #ty_generics #where_clause {
+            type Destroyer = #destroyer;
+
+            unsafe fn destroy_with(
+                &mut self,
+                destroyer: &Self::Destroyer,
+                allocation_callbacks: ::core::option::Option<
+                    &::ashpan::__private::vk::AllocationCallbacks,
+                >,
+            ) {
+                #(#destroy_calls)*
+            }
+
+            fn visit_handles(
+                &self,
+                visitor: &mut dyn ::core::ops::FnMut(::ashpan::__private::vk::ObjectType, u64),
+            ) {
+                #(#visit_calls)*
+            }
+        }
+    })
+}
+
+/// Returns the non-skipped fields in reverse declaration order.
+fn destroyed_fields(fields: &Fields) -> syn::Result<Vec<DestroyedField>> {
+    let mut destroyed_fields = Vec::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        let mut skip = false;
+        let mut order = None;
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("destroyable") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("order") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    order = Some((lit.base10_parse()?, lit.span()));
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip` or `order = N`"))
+                }
+            })?;
+        }
+
+        if skip {
+            if let Some((_, span)) = order {
+                return Err(Error::new(span, "skipped fields can't have an order"));
+            }
+            continue;
+        }
+
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        destroyed_fields.push(DestroyedField {
+            member,
+            ty: field.ty.clone(),
+            order,
+        });
+    }
+
+    destroyed_fields.reverse();
+    Ok(destroyed_fields)
+}
+
+/// Moves explicitly ordered fields to the front, sorted by order.
+fn order_fields(destroyed_fields: &mut [DestroyedField]) -> syn::Result<()> {
+    // Stable sort preserves reverse declaration order among unordered fields.
+    destroyed_fields.sort_by_key(|field| match field.order {
+        Some((order, _)) => (0, order),
+        None => (1, 0),
+    });
+
+    for pair in destroyed_fields.windows(2) {
+        if let (Some((a, _)), Some((b, span))) = (pair[0].order, pair[1].order) {
+            if a == b {
+                return Err(Error::new(span, format!("duplicate order {b}")));
+            }
+        }
+    }
+
+    Ok(())
+}
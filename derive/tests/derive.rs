@@ -0,0 +1,280 @@
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+
+use ash::vk;
+use ashpan::{Destroyable, Guarded};
+
+type Log = RefCell<Vec<&'static str>>;
+
+#[derive(Debug)]
+struct Logged(&'static str);
+
+impl Destroyable for Logged {
+    type Destroyer = Log;
+
+    unsafe fn destroy_with(
+        &mut self,
+        log: &Log,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        log.borrow_mut().push(self.0);
+    }
+}
+
+#[test]
+fn fields_are_destroyed_in_reverse_declaration_order() {
+    #[derive(Destroyable)]
+    struct Resources {
+        first: Logged,
+        second: Logged,
+        third: Logged,
+    }
+
+    let log = Log::default();
+    unsafe {
+        Guarded::new(
+            Resources {
+                first: Logged("first"),
+                second: Logged("second"),
+                third: Logged("third"),
+            },
+            &log,
+            None,
+        );
+    }
+
+    assert_eq!(*log.borrow(), ["third", "second", "first"]);
+}
+
+#[test]
+fn skipped_fields_are_not_destroyed() {
+    #[derive(Destroyable)]
+    struct Resources {
+        first: Logged,
+        #[destroyable(skip)]
+        _second: Logged,
+        third: Logged,
+    }
+
+    let log = Log::default();
+    unsafe {
+        Guarded::new(
+            Resources {
+                first: Logged("first"),
+                _second: Logged("second"),
+                third: Logged("third"),
+            },
+            &log,
+            None,
+        );
+    }
+
+    assert_eq!(*log.borrow(), ["third", "first"]);
+}
+
+#[test]
+fn ordered_fields_are_destroyed_first() {
+    #[derive(Destroyable)]
+    struct Resources {
+        a: Logged,
+        #[destroyable(order = 2)]
+        b: Logged,
+        c: Logged,
+        #[destroyable(order = 1)]
+        d: Logged,
+        e: Logged,
+    }
+
+    let log = Log::default();
+    unsafe {
+        Guarded::new(
+            Resources {
+                a: Logged("a"),
+                b: Logged("b"),
+                c: Logged("c"),
+                d: Logged("d"),
+                e: Logged("e"),
+            },
+            &log,
+            None,
+        );
+    }
+
+    assert_eq!(*log.borrow(), ["d", "b", "e", "c", "a"]);
+}
+
+#[test]
+fn tuple_structs_and_nested_containers_are_supported() {
+    #[derive(Destroyable)]
+    struct Resources(Vec<Logged>, Option<Logged>);
+
+    let log = Log::default();
+    unsafe {
+        Guarded::new(
+            Resources(
+                vec![Logged("vec 0"), Logged("vec 1")],
+                Some(Logged("option")),
+            ),
+            &log,
+            None,
+        );
+    }
+
+    assert_eq!(*log.borrow(), ["option", "vec 0", "vec 1"]);
+}
+
+#[test]
+fn generic_structs_are_supported() {
+    #[derive(Destroyable)]
+    struct Pair<T> {
+        first: T,
+        second: T,
+    }
+
+    let log = Log::default();
+    unsafe {
+        Guarded::new(
+            Pair {
+                first: Logged("first"),
+                second: Logged("second"),
+            },
+            &log,
+            None,
+        );
+    }
+
+    assert_eq!(*log.borrow(), ["second", "first"]);
+}
+
+#[test]
+fn explicit_destroyer_is_used() {
+    #[derive(Destroyable)]
+    #[destroyable(destroyer = ash::Device)]
+    struct Resources {
+        #[destroyable(skip)]
+        _name: &'static str,
+        _render_pass: vk::RenderPass,
+        _pipeline: vk::Pipeline,
+    }
+
+    fn destroyer_is_device<T: Destroyable<Destroyer = ash::Device>>() {}
+    destroyer_is_device::<Resources>();
+}
+
+#[test]
+fn handles_of_destroyed_fields_are_visited() {
+    use ash::vk::Handle;
+
+    #[derive(Destroyable)]
+    struct Resources {
+        render_pass: vk::RenderPass,
+        #[destroyable(skip)]
+        _unowned: vk::Buffer,
+        pipelines: Vec<vk::Pipeline>,
+    }
+
+    let resources = Resources {
+        render_pass: vk::RenderPass::from_raw(1),
+        _unowned: vk::Buffer::from_raw(2),
+        pipelines: vec![vk::Pipeline::from_raw(3), vk::Pipeline::from_raw(4)],
+    };
+    let mut handles = Vec::new();
+    resources.visit_handles(&mut |object_type, handle| handles.push((object_type, handle)));
+
+    assert_eq!(
+        handles,
+        [
+            (vk::ObjectType::PIPELINE, 3),
+            (vk::ObjectType::PIPELINE, 4),
+            (vk::ObjectType::RENDER_PASS, 1),
+        ]
+    );
+}
+
+#[test]
+fn explicit_destroyer_provides_field_destroyers() {
+    struct Context {
+        log: Log,
+    }
+
+    impl ashpan::DestroyerSource<Log> for Context {
+        fn destroyer(&self) -> Option<&Log> {
+            Some(&self.log)
+        }
+    }
+
+    #[derive(Destroyable)]
+    #[destroyable(destroyer = Context)]
+    struct Resources {
+        first: Logged,
+        second: Logged,
+    }
+
+    let context = Context {
+        log: Log::default(),
+    };
+    unsafe {
+        Guarded::new(
+            Resources {
+                first: Logged("first"),
+                second: Logged("second"),
+            },
+            &context,
+            None,
+        );
+    }
+
+    assert_eq!(*context.log.borrow(), ["second", "first"]);
+}
+
+#[test]
+fn unavailable_field_destroyers_leave_fields_alone_and_are_reported() {
+    struct Context {
+        log: Log,
+    }
+
+    impl ashpan::DestroyerSource<Log> for Context {
+        fn destroyer(&self) -> Option<&Log> {
+            None
+        }
+    }
+
+    #[derive(Destroyable)]
+    #[destroyable(destroyer = Context)]
+    struct Resources {
+        _first: Logged,
+    }
+
+    let context = Context {
+        log: Log::default(),
+    };
+    let resources = unsafe {
+        Guarded::new(
+            Resources {
+                _first: Logged("first"),
+            },
+            &context,
+            None,
+        )
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| drop(resources)));
+
+    assert!(context.log.borrow().is_empty());
+    if cfg!(debug_assertions) {
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("Logged"), "{message}");
+    }
+}
+
+#[test]
+fn device_context_mixes_core_and_extension_destroyers() {
+    #[derive(Destroyable)]
+    #[destroyable(destroyer = ashpan::DeviceContext)]
+    struct Presentation {
+        _swapchain: vk::SwapchainKHR,
+        _image_views: Vec<vk::ImageView>,
+    }
+
+    fn destroyer_is_device_context<T: Destroyable<Destroyer = ashpan::DeviceContext>>() {}
+    destroyer_is_device_context::<Presentation>();
+}
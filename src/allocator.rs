@@ -0,0 +1,340 @@
+use std::alloc::{self, Layout};
+use std::ffi::c_void;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ash::vk;
+
+/// [`vk::AllocationCallbacks`] backed by Rust's global allocator, with usage tracking
+///
+/// Host memory that Vulkan allocates via [`callbacks`](Self::callbacks) is counted per
+/// [`vk::SystemAllocationScope`], including peak usage. Because the callbacks borrow the
+/// [`TrackingAllocator`], guards created with them can't outlive it, which makes it easy to check
+/// that all host memory was returned once they're dropped:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{DeviceExt, TrackingAllocator};
+///
+/// unsafe fn check_for_host_leaks(device: &ash::Device) -> VkResult<()> {
+///     let allocator = TrackingAllocator::new();
+///     {
+///         let create_info = unimplemented!();
+///         let _buffer = device.create_guarded_buffer(create_info, Some(allocator.callbacks()))?;
+///         // ...
+///     }
+///     assert_eq!(allocator.total_allocated_bytes(), 0);
+///     Ok(())
+/// }
+/// ```
+///
+/// Counts only include the sizes that Vulkan requested, not bookkeeping overhead. Internal
+/// allocation notifications aren't tracked.
+pub struct TrackingAllocator {
+    // Boxed so that p_user_data stays valid if the TrackingAllocator moves.
+    state: Box<State>,
+    callbacks: vk::AllocationCallbacks,
+}
+
+// The callbacks only refer to `state`, which is made of atomics.
+unsafe impl Send for TrackingAllocator {}
+unsafe impl Sync for TrackingAllocator {}
+
+#[derive(Default)]
+struct State {
+    scopes: [Usage; SCOPE_COUNT],
+}
+
+#[derive(Default)]
+struct Usage {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+// COMMAND, OBJECT, CACHE, DEVICE and INSTANCE
+const SCOPE_COUNT: usize = 5;
+
+/// Stored immediately before each allocation handed out to Vulkan
+struct Header {
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+}
+
+impl TrackingAllocator {
+    /// Creates a [`TrackingAllocator`] with no outstanding allocations.
+    pub fn new() -> Self {
+        let state = Box::<State>::default();
+        let callbacks = vk::AllocationCallbacks {
+            p_user_data: &*state as *const State as *mut c_void,
+            pfn_allocation: Some(allocation),
+            pfn_reallocation: Some(reallocation),
+            pfn_free: Some(free),
+            pfn_internal_allocation: None,
+            pfn_internal_free: None,
+        };
+        Self { state, callbacks }
+    }
+
+    /// Returns allocation callbacks to pass to Vulkan.
+    pub fn callbacks(&self) -> &vk::AllocationCallbacks {
+        &self.callbacks
+    }
+
+    /// Returns the number of bytes currently allocated in `scope`.
+    pub fn allocated_bytes(&self, scope: vk::SystemAllocationScope) -> usize {
+        self.state.usage(scope).current.load(Ordering::Relaxed)
+    }
+
+    /// Returns the largest number of bytes that have been allocated in `scope` at once.
+    pub fn peak_bytes(&self, scope: vk::SystemAllocationScope) -> usize {
+        self.state.usage(scope).peak.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes currently allocated across all scopes.
+    pub fn total_allocated_bytes(&self) -> usize {
+        self.state
+            .scopes
+            .iter()
+            .map(|usage| usage.current.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+impl Default for TrackingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TrackingAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackingAllocator")
+            .field("total_allocated_bytes", &self.total_allocated_bytes())
+            .finish()
+    }
+}
+
+impl State {
+    fn usage(&self, scope: vk::SystemAllocationScope) -> &Usage {
+        // Unknown scopes (from a newer Vulkan) are counted as INSTANCE.
+        let index = (scope.as_raw().max(0) as usize).min(SCOPE_COUNT - 1);
+        &self.scopes[index]
+    }
+
+    fn add(&self, scope: vk::SystemAllocationScope, size: usize) {
+        let usage = self.usage(scope);
+        let current = usage.current.fetch_add(size, Ordering::Relaxed) + size;
+        usage.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn remove(&self, scope: vk::SystemAllocationScope, size: usize) {
+        self.usage(scope).current.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// Returns the layout of the whole block and the offset of the memory handed to Vulkan.
+fn block_layout(size: usize, alignment: usize) -> Option<(Layout, usize)> {
+    let alignment = alignment.max(mem::align_of::<Header>());
+    let offset = mem::size_of::<Header>().checked_next_multiple_of(alignment)?;
+    let layout = Layout::from_size_align(offset.checked_add(size)?, alignment).ok()?;
+    Some((layout, offset))
+}
+
+unsafe fn header<'a>(memory: *mut c_void) -> &'a Header {
+    &*(memory as *const Header).sub(1)
+}
+
+unsafe fn allocate(
+    state: &State,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    let (layout, offset) = match block_layout(size, alignment) {
+        Some(layout) if alignment.is_power_of_two() => layout,
+        _ => return ptr::null_mut(),
+    };
+    let block = alloc::alloc(layout);
+    if block.is_null() {
+        return ptr::null_mut();
+    }
+
+    let memory = block.add(offset) as *mut c_void;
+    (memory as *mut Header).sub(1).write(Header {
+        size,
+        alignment,
+        scope,
+    });
+    state.add(scope, size);
+    memory
+}
+
+unsafe fn deallocate(state: &State, memory: *mut c_void) {
+    let Header {
+        size,
+        alignment,
+        scope,
+    } = *header(memory);
+    let (layout, offset) = block_layout(size, alignment).unwrap();
+    alloc::dealloc((memory as *mut u8).sub(offset), layout);
+    state.remove(scope, size);
+}
+
+unsafe extern "system" fn allocation(
+    user_data: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    allocate(&*(user_data as *const State), size, alignment, scope)
+}
+
+unsafe extern "system" fn reallocation(
+    user_data: *mut c_void,
+    original: *mut c_void,
+    size: usize,
+    alignment: usize,
+    scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    let state = &*(user_data as *const State);
+    if original.is_null() {
+        return allocate(state, size, alignment, scope);
+    }
+    if size == 0 {
+        deallocate(state, original);
+        return ptr::null_mut();
+    }
+
+    // On failure, the original allocation must be left untouched.
+    let memory = allocate(state, size, alignment, scope);
+    if !memory.is_null() {
+        let original_size = header(original).size;
+        ptr::copy_nonoverlapping(
+            original as *const u8,
+            memory as *mut u8,
+            original_size.min(size),
+        );
+        deallocate(state, original);
+    }
+    memory
+}
+
+unsafe extern "system" fn free(user_data: *mut c_void, memory: *mut c_void) {
+    if !memory.is_null() {
+        deallocate(&*(user_data as *const State), memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_void;
+    use std::ptr;
+
+    use ash::vk;
+
+    use crate::{mock, DeviceExt, TrackingAllocator};
+
+    unsafe fn allocate(
+        callbacks: &vk::AllocationCallbacks,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        (callbacks.pfn_allocation.unwrap())(callbacks.p_user_data, size, alignment, scope)
+    }
+
+    unsafe fn reallocate(
+        callbacks: &vk::AllocationCallbacks,
+        original: *mut c_void,
+        size: usize,
+        alignment: usize,
+        scope: vk::SystemAllocationScope,
+    ) -> *mut c_void {
+        (callbacks.pfn_reallocation.unwrap())(
+            callbacks.p_user_data,
+            original,
+            size,
+            alignment,
+            scope,
+        )
+    }
+
+    unsafe fn free(callbacks: &vk::AllocationCallbacks, memory: *mut c_void) {
+        (callbacks.pfn_free.unwrap())(callbacks.p_user_data, memory)
+    }
+
+    #[test]
+    fn allocations_are_aligned_and_counted_per_scope() {
+        let allocator = TrackingAllocator::new();
+        let callbacks = allocator.callbacks();
+
+        unsafe {
+            let a = allocate(callbacks, 100, 256, vk::SystemAllocationScope::OBJECT);
+            let b = allocate(callbacks, 24, 8, vk::SystemAllocationScope::COMMAND);
+            assert_eq!(a as usize % 256, 0);
+            assert_eq!(b as usize % 8, 0);
+            assert_eq!(
+                allocator.allocated_bytes(vk::SystemAllocationScope::OBJECT),
+                100
+            );
+            assert_eq!(
+                allocator.allocated_bytes(vk::SystemAllocationScope::COMMAND),
+                24
+            );
+            assert_eq!(allocator.total_allocated_bytes(), 124);
+
+            free(callbacks, a);
+            free(callbacks, b);
+            free(callbacks, ptr::null_mut());
+        }
+
+        assert_eq!(allocator.total_allocated_bytes(), 0);
+        assert_eq!(allocator.peak_bytes(vk::SystemAllocationScope::OBJECT), 100);
+        assert_eq!(allocator.peak_bytes(vk::SystemAllocationScope::DEVICE), 0);
+    }
+
+    #[test]
+    fn reallocation_preserves_contents() {
+        let allocator = TrackingAllocator::new();
+        let callbacks = allocator.callbacks();
+        let scope = vk::SystemAllocationScope::CACHE;
+
+        unsafe {
+            let memory = reallocate(callbacks, ptr::null_mut(), 4, 64, scope) as *mut u8;
+            ptr::copy_nonoverlapping([1, 2, 3, 4].as_ptr(), memory, 4);
+
+            let memory = reallocate(callbacks, memory as _, 1000, 64, scope) as *mut u8;
+            assert_eq!(memory as usize % 64, 0);
+            assert_eq!(std::slice::from_raw_parts(memory, 4), [1, 2, 3, 4]);
+            assert_eq!(allocator.allocated_bytes(scope), 1000);
+
+            let memory = reallocate(callbacks, memory as _, 2, 64, scope) as *mut u8;
+            assert_eq!(std::slice::from_raw_parts(memory, 2), [1, 2]);
+            assert_eq!(allocator.allocated_bytes(scope), 2);
+
+            assert!(reallocate(callbacks, memory as _, 0, 64, scope).is_null());
+        }
+
+        assert_eq!(allocator.allocated_bytes(scope), 0);
+        // The original and reallocated blocks briefly coexist
+        assert_eq!(allocator.peak_bytes(scope), 1004);
+    }
+
+    #[test]
+    fn guards_borrow_callbacks() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocator = TrackingAllocator::new();
+
+        let buffer = unsafe {
+            (&device).create_guarded_buffer(&Default::default(), Some(allocator.callbacks()))
+        }
+        .unwrap();
+        assert_eq!(
+            buffer.allocation_callbacks().map(|a| a as *const _),
+            Some(allocator.callbacks() as *const _)
+        );
+    }
+}
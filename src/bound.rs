@@ -0,0 +1,271 @@
+use ash::{prelude::VkResult, vk};
+
+use crate::{Destroyable, DeviceExt, GuardedResource};
+
+/// Picks a memory type for an allocation based on required and preferred property flags
+///
+/// Memory types are looked up in `memory_properties`, as returned by
+/// [`get_physical_device_memory_properties`](ash::Instance::get_physical_device_memory_properties).
+/// [`select`](Self::select) only considers types that have all `required` flags, and among
+/// those picks the first one that also has all `preferred` flags, falling back to the first one
+/// that doesn't:
+///
+/// ```
+/// # use ash::vk;
+/// use ashpan::MemoryTypeSelector;
+///
+/// fn staging_memory_type(
+///     memory_properties: &vk::PhysicalDeviceMemoryProperties,
+///     memory_type_bits: u32,
+/// ) -> Option<u32> {
+///     MemoryTypeSelector::new(
+///         memory_properties,
+///         vk::MemoryPropertyFlags::HOST_VISIBLE,
+///         vk::MemoryPropertyFlags::HOST_COHERENT,
+///     )
+///     .select(memory_type_bits)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryTypeSelector<'p> {
+    memory_properties: &'p vk::PhysicalDeviceMemoryProperties,
+    required: vk::MemoryPropertyFlags,
+    preferred: vk::MemoryPropertyFlags,
+}
+
+impl<'p> MemoryTypeSelector<'p> {
+    /// Creates a [`MemoryTypeSelector`] that picks from the memory types in `memory_properties`.
+    pub fn new(
+        memory_properties: &'p vk::PhysicalDeviceMemoryProperties,
+        required: vk::MemoryPropertyFlags,
+        preferred: vk::MemoryPropertyFlags,
+    ) -> Self {
+        Self {
+            memory_properties,
+            required,
+            preferred,
+        }
+    }
+
+    /// Returns the index of the best memory type among those whose bit is set in
+    /// `memory_type_bits` (e.g. from [`vk::MemoryRequirements`]), or `None` if none of them have
+    /// the required flags.
+    pub fn select(&self, memory_type_bits: u32) -> Option<u32> {
+        let memory_types = &self.memory_properties.memory_types
+            [..self.memory_properties.memory_type_count as usize];
+        let candidates = (0..).zip(memory_types).filter(|&(index, memory_type)| {
+            memory_type_bits & (1 << index) != 0
+                && memory_type.property_flags.contains(self.required)
+        });
+
+        let mut fallback = None;
+        for (index, memory_type) in candidates {
+            if memory_type.property_flags.contains(self.preferred) {
+                return Some(index);
+            }
+            fallback = fallback.or(Some(index));
+        }
+        fallback
+    }
+}
+
+/// [`vk::Buffer`] along with the [`vk::DeviceMemory`] bound to it
+///
+/// Created by [`DeviceExt::create_guarded_bound_buffer`]. The buffer is destroyed before its
+/// memory is freed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundBuffer {
+    /// The buffer
+    pub buffer: vk::Buffer,
+    /// Memory bound to `buffer` at offset 0
+    pub memory: vk::DeviceMemory,
+}
+
+impl Destroyable for BoundBuffer {
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        self.buffer.destroy_with(device, allocation_callbacks);
+        self.memory.destroy_with(device, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        self.buffer.visit_handles(visitor);
+        self.memory.visit_handles(visitor);
+    }
+}
+
+/// [`vk::Image`] along with the [`vk::DeviceMemory`] bound to it
+///
+/// Created by [`DeviceExt::create_guarded_bound_image`]. The image is destroyed before its memory
+/// is freed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundImage {
+    /// The image
+    pub image: vk::Image,
+    /// Memory bound to `image` at offset 0
+    pub memory: vk::DeviceMemory,
+}
+
+impl Destroyable for BoundImage {
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        self.image.destroy_with(device, allocation_callbacks);
+        self.memory.destroy_with(device, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        self.image.visit_handles(visitor);
+        self.memory.visit_handles(visitor);
+    }
+}
+
+/// Allocates memory that satisfies `requirements` from the type picked by `memory_types`, or
+/// returns [`vk::Result::ERROR_OUT_OF_DEVICE_MEMORY`] if there's no suitable type.
+pub(crate) unsafe fn allocate_guarded_memory_for<'a, DeviceRef: DeviceExt + Clone>(
+    device: &DeviceRef,
+    requirements: &vk::MemoryRequirements,
+    memory_types: &MemoryTypeSelector,
+    allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+) -> VkResult<GuardedResource<'a, vk::DeviceMemory, DeviceRef>> {
+    let memory_type_index = memory_types
+        .select(requirements.memory_type_bits)
+        .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+    let allocate_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type_index);
+    device.allocate_guarded_memory(&allocate_info, allocation_callbacks)
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::{mock, DeviceExt, MemoryTypeSelector};
+
+    fn memory_properties(flags: &[vk::MemoryPropertyFlags]) -> vk::PhysicalDeviceMemoryProperties {
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: flags.len() as u32,
+            ..Default::default()
+        };
+        for (memory_type, &property_flags) in memory_properties.memory_types.iter_mut().zip(flags) {
+            memory_type.property_flags = property_flags;
+        }
+        memory_properties
+    }
+
+    #[test]
+    fn preferred_memory_types_are_selected_over_earlier_ones() {
+        let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        let host_coherent = vk::MemoryPropertyFlags::HOST_COHERENT;
+        let memory_properties = memory_properties(&[
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            host_visible,
+            host_visible | host_coherent,
+            host_visible | host_coherent,
+        ]);
+        let selector = MemoryTypeSelector::new(&memory_properties, host_visible, host_coherent);
+
+        assert_eq!(selector.select(0b1111), Some(2));
+        assert_eq!(selector.select(0b1011), Some(3));
+        // Falls back to types that only have the required flags
+        assert_eq!(selector.select(0b0011), Some(1));
+        assert_eq!(selector.select(0b0001), None);
+        // Bits beyond memory_type_count are ignored
+        assert_eq!(selector.select(0b1_0000), None);
+    }
+
+    #[test]
+    fn bound_buffer_is_destroyed_before_its_memory_is_freed() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let memory_properties = memory_properties(&[vk::MemoryPropertyFlags::DEVICE_LOCAL]);
+        let memory_types =
+            MemoryTypeSelector::new(&memory_properties, Default::default(), Default::default());
+
+        let bound = unsafe {
+            (&device).create_guarded_bound_buffer(
+                &Default::default(),
+                &memory_types,
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let (buffer, memory) = (bound.buffer, bound.memory);
+        drop(bound);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateBuffer", buffer, Some(&allocation_callbacks)),
+                mock::Call::new("vkAllocateMemory", memory, Some(&allocation_callbacks)),
+                mock::Call::new("vkBindBufferMemory", buffer, None),
+                mock::Call::new("vkDestroyBuffer", buffer, Some(&allocation_callbacks)),
+                mock::Call::new("vkFreeMemory", memory, Some(&allocation_callbacks)),
+            ]
+        );
+    }
+
+    #[test]
+    fn bound_image_is_destroyed_before_its_memory_is_freed() {
+        let (_instance, device) = mock::instance_and_device();
+        let memory_properties = memory_properties(&[vk::MemoryPropertyFlags::DEVICE_LOCAL]);
+        let memory_types = MemoryTypeSelector::new(
+            &memory_properties,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            Default::default(),
+        );
+
+        let bound = unsafe {
+            (&device).create_guarded_bound_image(&Default::default(), &memory_types, None)
+        }
+        .unwrap();
+        let (image, memory) = (bound.image, bound.memory);
+        drop(bound);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateImage", image, None),
+                mock::Call::new("vkAllocateMemory", memory, None),
+                mock::Call::new("vkBindImageMemory", image, None),
+                mock::Call::new("vkDestroyImage", image, None),
+                mock::Call::new("vkFreeMemory", memory, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn buffer_is_destroyed_when_no_memory_type_matches() {
+        let (_instance, device) = mock::instance_and_device();
+        let memory_properties = memory_properties(&[vk::MemoryPropertyFlags::DEVICE_LOCAL]);
+        let memory_types = MemoryTypeSelector::new(
+            &memory_properties,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+            Default::default(),
+        );
+
+        let result = unsafe {
+            (&device).create_guarded_bound_buffer(&Default::default(), &memory_types, None)
+        };
+        assert!(matches!(
+            result,
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+        ));
+
+        let functions: Vec<_> = mock::calls(&device)[1..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(functions, ["vkCreateBuffer", "vkDestroyBuffer"]);
+    }
+}
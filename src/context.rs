@@ -0,0 +1,399 @@
+use std::any::type_name;
+use std::fmt;
+
+use ash::{
+    extensions::{ext, khr, nv},
+    prelude::VkResult,
+    vk::{self, Handle},
+};
+
+use crate::{Destroyable, GuardedResource};
+
+/// Something that can provide a [`Destroyable::Destroyer`]
+///
+/// This lets a single destroyer stand in for several, so that a struct whose fields have
+/// different destroyers can still implement [`Destroyable`]. Every type is a source of itself;
+/// [`DeviceContext`] is a source of [`ash::Device`] and the extension loaders it holds.
+pub trait DestroyerSource<Destroyer: ?Sized> {
+    /// Returns the destroyer, or `None` if it isn't available, e.g. if a [`DeviceContext`]
+    /// wasn't given the requested loader.
+    fn destroyer(&self) -> Option<&Destroyer>;
+}
+
+impl<Destroyer: ?Sized> DestroyerSource<Destroyer> for Destroyer {
+    fn destroyer(&self) -> Option<&Destroyer> {
+        Some(self)
+    }
+}
+
+/// Destroyer bundling an [`ash::Device`] with optional device-level extension loaders
+///
+/// A `Guarded<vk::Buffer>` is destroyed by an [`ash::Device`] but a `Guarded<vk::SwapchainKHR>`
+/// is destroyed by a [`khr::Swapchain`], so a struct holding both can't pick either one as its
+/// [`Destroyer`](Destroyable::Destroyer). It can pick [`DeviceContext`] instead, and obtain each
+/// field's destroyer from it via [`DestroyerSource`]:
+///
+/// ```
+/// # use ash::{extensions::khr, prelude::VkResult, vk};
+/// use ashpan::{Destroyable, DeviceContext};
+///
+/// struct Presentation {
+///     swapchain: vk::SwapchainKHR,
+///     image_views: Vec<vk::ImageView>,
+/// }
+///
+/// impl Destroyable for Presentation {
+///     type Destroyer = DeviceContext;
+///
+///     unsafe fn destroy_with(
+///         &mut self,
+///         context: &DeviceContext,
+///         allocation_callbacks: Option<&vk::AllocationCallbacks>,
+///     ) {
+///         context.destroy(&mut self.image_views, allocation_callbacks);
+///         context.destroy(&mut self.swapchain, allocation_callbacks);
+///     }
+///
+///     fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+///         self.image_views.visit_handles(visitor);
+///         self.swapchain.visit_handles(visitor);
+///     }
+/// }
+///
+/// unsafe fn guard(
+///     instance: &ash::Instance,
+///     device: &ash::Device,
+///     presentation: Presentation,
+/// ) -> VkResult<()> {
+///     let context =
+///         DeviceContext::new(device).with_swapchain(khr::Swapchain::new(instance, device));
+///     let presentation = context.guard(presentation, None)?;
+///     // ...
+/// #   Ok(())
+/// }
+/// ```
+///
+/// [`guard`](Self::guard) refuses resources whose loader is missing. If such a resource is
+/// guarded anyway, it's left alone (i.e. leaked) when destroyed; debug builds panic, and with the
+/// `leak-tracking` feature its handles are flagged in the leak report. With the `derive` feature,
+/// `#[destroyable(destroyer = DeviceContext)]` does the same as the above [`Destroyable`]
+/// implementation.
+#[derive(Clone)]
+pub struct DeviceContext {
+    device: ash::Device,
+    acceleration_structure: Option<khr::AccelerationStructure>,
+    deferred_host_operations: Option<khr::DeferredHostOperations>,
+    private_data: Option<ext::PrivateData>,
+    ray_tracing_nv: Option<nv::RayTracing>,
+    swapchain: Option<khr::Swapchain>,
+}
+
+impl DeviceContext {
+    /// Creates a [`DeviceContext`] without any extension loaders.
+    pub fn new(device: &ash::Device) -> Self {
+        Self {
+            device: device.clone(),
+            acceleration_structure: None,
+            deferred_host_operations: None,
+            private_data: None,
+            ray_tracing_nv: None,
+            swapchain: None,
+        }
+    }
+
+    /// Returns the device.
+    pub fn device(&self) -> &ash::Device {
+        &self.device
+    }
+
+    /// Destroys `resource` with the destroyer that `self` provides for it. If `self` doesn't have
+    /// the loader that destroys `resource`, `resource` is left alone and reported as leaked
+    /// instead.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Destroyable::destroy_with`].
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `self` doesn't have the loader that destroys `resource`.
+    pub unsafe fn destroy<Resource>(
+        &self,
+        resource: &mut Resource,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) where
+        Resource: Destroyable + ?Sized,
+        Self: DestroyerSource<Resource::Destroyer>,
+    {
+        match self.destroyer() {
+            Some(destroyer) => resource.destroy_with(destroyer, allocation_callbacks),
+            None => destroyer_unavailable(resource),
+        }
+    }
+
+    /// Guards `resource` with `self` after [checking](Self::check_loaders) that `self` has the
+    /// loaders that destroy it.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`GuardedResource::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`vk::Result::ERROR_EXTENSION_NOT_PRESENT`] if a loader is missing, in which case
+    /// `resource` is dropped without being destroyed. Use [`check_loaders`](Self::check_loaders)
+    /// beforehand to keep it.
+    pub unsafe fn guard<'a, Resource>(
+        &self,
+        resource: Resource,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, Resource, &Self>>
+    where
+        Resource: Destroyable<Destroyer = DeviceContext>,
+    {
+        self.check_loaders(&resource)?;
+        Ok(GuardedResource::new(resource, self, allocation_callbacks))
+    }
+
+    /// Checks that `self` has the loaders that destroy the non-null handles reported by
+    /// `resource`'s [`visit_handles`](Destroyable::visit_handles), so that guarding `resource`
+    /// with `self` won't leak them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`vk::Result::ERROR_EXTENSION_NOT_PRESENT`] if a loader is missing.
+    pub fn check_loaders<Resource>(&self, resource: &Resource) -> VkResult<()>
+    where
+        Resource: Destroyable + ?Sized,
+    {
+        let mut result = Ok(());
+        resource.visit_handles(&mut |object_type, handle| {
+            if handle != 0 && !self.has_loader_for(object_type) {
+                result = Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT);
+            }
+        });
+        result
+    }
+}
+
+/// Reports that `resource` was left alone because its destroyer isn't available: its handles are
+/// flagged in the leak report if the `leak-tracking` feature is enabled, and debug builds panic.
+#[cfg_attr(not(feature = "leak-tracking"), allow(unused_variables))]
+pub fn destroyer_unavailable<Resource: Destroyable + ?Sized>(resource: &Resource) {
+    #[cfg(feature = "leak-tracking")]
+    crate::leak::flag_destroyer_unavailable(resource);
+    debug_assert!(
+        false,
+        "leaked {} because its destroyer is unavailable",
+        type_name::<Resource>()
+    );
+}
+
+macro_rules! loaders {
+    ($($field:ident, $with:ident, $Loader:ty, $Resource:ty,)*) => {
+        impl DeviceContext {
+            $(
+                #[doc = concat!("Adds a [`", stringify!($Loader), "`] loader.")]
+                pub fn $with(mut self, loader: $Loader) -> Self {
+                    self.$field = Some(loader);
+                    self
+                }
+
+                #[doc = concat!("Returns the [`", stringify!($Loader), "`] loader, if any.")]
+                pub fn $field(&self) -> Option<&$Loader> {
+                    self.$field.as_ref()
+                }
+            )*
+
+            fn has_loader_for(&self, object_type: vk::ObjectType) -> bool {
+                $(
+                    if object_type == <$Resource>::TYPE {
+                        return self.$field.is_some();
+                    }
+                )*
+                // Everything else is destroyed by the device.
+                true
+            }
+        }
+
+        $(
+            impl DestroyerSource<$Loader> for DeviceContext {
+                fn destroyer(&self) -> Option<&$Loader> {
+                    self.$field.as_ref()
+                }
+            }
+        )*
+
+        impl fmt::Debug for DeviceContext {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("DeviceContext")
+                    .field("device", &self.device.handle())
+                    $(.field(stringify!($field), &self.$field.is_some()))*
+                    .finish()
+            }
+        }
+    };
+}
+
+loaders!(
+    acceleration_structure,
+    with_acceleration_structure,
+    khr::AccelerationStructure,
+    vk::AccelerationStructureKHR,
+    deferred_host_operations,
+    with_deferred_host_operations,
+    khr::DeferredHostOperations,
+    vk::DeferredOperationKHR,
+    private_data,
+    with_private_data,
+    ext::PrivateData,
+    vk::PrivateDataSlotEXT,
+    ray_tracing_nv,
+    with_ray_tracing_nv,
+    nv::RayTracing,
+    vk::AccelerationStructureNV,
+    swapchain,
+    with_swapchain,
+    khr::Swapchain,
+    vk::SwapchainKHR,
+);
+
+impl DestroyerSource<ash::Device> for DeviceContext {
+    fn destroyer(&self) -> Option<&ash::Device> {
+        Some(&self.device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use ash::{extensions::khr, vk};
+
+    use crate::{mock, Destroyable, DeviceContext, DeviceExt, Guarded, SwapchainExt};
+
+    struct Presentation {
+        swapchain: vk::SwapchainKHR,
+        image_view: vk::ImageView,
+    }
+
+    impl Destroyable for Presentation {
+        type Destroyer = DeviceContext;
+
+        unsafe fn destroy_with(
+            &mut self,
+            context: &DeviceContext,
+            allocation_callbacks: Option<&vk::AllocationCallbacks>,
+        ) {
+            context.destroy(&mut self.image_view, allocation_callbacks);
+            context.destroy(&mut self.swapchain, allocation_callbacks);
+        }
+
+        fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+            self.image_view.visit_handles(visitor);
+            self.swapchain.visit_handles(visitor);
+        }
+    }
+
+    #[test]
+    fn fields_are_destroyed_by_their_own_destroyers() {
+        let (instance, device) = mock::instance_and_device();
+        let swapchain_fn = khr::Swapchain::new(&instance, &device);
+        let context = DeviceContext::new(&device).with_swapchain(swapchain_fn.clone());
+
+        let presentation = unsafe {
+            let swapchain = (&swapchain_fn)
+                .create_guarded_swapchain(&Default::default(), None)
+                .unwrap();
+            let image_view = (&device)
+                .create_guarded_image_view(&Default::default(), None)
+                .unwrap();
+            Presentation {
+                swapchain: swapchain.take(),
+                image_view: image_view.take(),
+            }
+        };
+        let (swapchain, image_view) = (presentation.swapchain, presentation.image_view);
+        drop(unsafe { Guarded::new(presentation, &context, None) });
+
+        assert_eq!(
+            mock::calls(&device)[3..],
+            [
+                mock::Call::new("vkDestroyImageView", image_view, None),
+                mock::Call::new("vkDestroySwapchainKHR", swapchain, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_loaders_are_detected_before_guarding() {
+        let (instance, device) = mock::instance_and_device();
+        let swapchain_fn = khr::Swapchain::new(&instance, &device);
+        let context = DeviceContext::new(&device);
+        let mut presentation = Presentation {
+            swapchain: vk::SwapchainKHR::null(),
+            image_view: vk::ImageView::null(),
+        };
+        assert_eq!(context.check_loaders(&presentation), Ok(()));
+
+        presentation.swapchain =
+            unsafe { (&swapchain_fn).create_guarded_swapchain(&Default::default(), None) }
+                .unwrap()
+                .take();
+        assert_eq!(
+            context.check_loaders(&presentation),
+            Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT)
+        );
+        assert_eq!(
+            unsafe { context.guard(presentation, None) }.err(),
+            Some(vk::Result::ERROR_EXTENSION_NOT_PRESENT)
+        );
+
+        let context = context.with_swapchain(swapchain_fn);
+        let presentation = Presentation {
+            swapchain: vk::SwapchainKHR::null(),
+            image_view: vk::ImageView::null(),
+        };
+        assert_eq!(context.check_loaders(&presentation), Ok(()));
+        assert!(unsafe { context.guard(presentation, None) }.is_ok());
+    }
+
+    #[test]
+    fn resources_without_loaders_are_reported_as_leaked() {
+        let (instance, device) = mock::instance_and_device();
+        let swapchain_fn = khr::Swapchain::new(&instance, &device);
+        let presentation = unsafe {
+            Presentation {
+                swapchain: (&swapchain_fn)
+                    .create_guarded_swapchain(&Default::default(), None)
+                    .unwrap()
+                    .take(),
+                image_view: vk::ImageView::null(),
+            }
+        };
+        let swapchain = presentation.swapchain;
+        let context = DeviceContext::new(&device);
+
+        let presentation = unsafe { Guarded::new(presentation, &context, None) };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(presentation)));
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateSwapchainKHR", swapchain, None),
+                mock::Call::new("vkDestroyImageView", vk::ImageView::null(), None),
+            ]
+        );
+
+        #[cfg(feature = "leak-tracking")]
+        {
+            use ash::vk::Handle;
+
+            let live_resource = crate::leak::report_live_resources()
+                .into_iter()
+                .find(|live_resource| live_resource.handle == swapchain.as_raw())
+                .unwrap();
+            assert!(live_resource.destroyer_unavailable);
+        }
+    }
+}
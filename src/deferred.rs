@@ -0,0 +1,266 @@
+use std::ops::Deref;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::destroy::drop_last_to_first;
+use crate::{Destroyable, GuardedResource};
+
+/// Queue of guarded resources whose destruction waits until the GPU is done with them
+///
+/// Dropping a [`GuardedResource`] destroys its resource immediately, which is invalid while
+/// in-flight GPU work still uses it. Instead, guards can be handed to a [`DeletionQueue`] along
+/// with a condition: either a [`vk::Fence`] that signals once the work completes, or a number of
+/// frames. Resources are destroyed (with the same destroyer and `allocation_callbacks` as the
+/// guard) by [`collect`](Self::collect) once their condition is met.
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{DeletionQueue, Guarded};
+///
+/// unsafe fn replace_vertex_buffer<'d>(
+///     deletion_queue: &mut DeletionQueue<'d, &'d ash::Device>,
+///     vertex_buffer: &mut Guarded<'d, vk::Buffer>,
+///     new_vertex_buffer: Guarded<'d, vk::Buffer>,
+///     frame_fence: vk::Fence,
+/// ) -> VkResult<()> {
+///     let old_vertex_buffer = std::mem::replace(vertex_buffer, new_vertex_buffer);
+///     // The previous frame may still be reading from the old buffer
+///     old_vertex_buffer.defer_until(deletion_queue, frame_fence);
+///     deletion_queue.collect()
+/// }
+/// ```
+///
+/// When several resources become ready at once, they're destroyed in the reverse order that
+/// they were queued, matching the order in which guards would've been dropped. Dropping the
+/// [`DeletionQueue`] destroys everything still queued (also in reverse order) regardless of
+/// conditions, so make sure the device is idle first.
+pub struct DeletionQueue<'a, DeviceRef>
+where
+    DeviceRef: Deref<Target = ash::Device>,
+{
+    device: DeviceRef,
+    frame: u64,
+    entries: Vec<Entry<'a>>,
+}
+
+struct Entry<'a> {
+    condition: Condition,
+    // Dropping the guard destroys the resource.
+    _guarded: Box<dyn Pending + 'a>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Condition {
+    Fence(vk::Fence),
+    Frame(u64),
+}
+
+trait Pending {}
+
+impl<T> Pending for T {}
+
+impl<'a, DeviceRef> DeletionQueue<'a, DeviceRef>
+where
+    DeviceRef: Deref<Target = ash::Device>,
+{
+    /// Creates an empty [`DeletionQueue`]. `device` is used to query fence status.
+    pub fn new(device: DeviceRef) -> Self {
+        Self {
+            device,
+            frame: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Queues `guarded` to be destroyed once `fence` has signaled.
+    pub fn defer_until<Resource, Destroyer>(
+        &mut self,
+        fence: vk::Fence,
+        guarded: GuardedResource<'a, Resource, Destroyer>,
+    ) where
+        Resource: Destroyable + 'a,
+        Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer> + 'a,
+    {
+        self.push(Condition::Fence(fence), guarded);
+    }
+
+    /// Queues `guarded` to be destroyed once [`next_frame`](Self::next_frame) has been called
+    /// `frames` times.
+    pub fn defer_frames<Resource, Destroyer>(
+        &mut self,
+        frames: u64,
+        guarded: GuardedResource<'a, Resource, Destroyer>,
+    ) where
+        Resource: Destroyable + 'a,
+        Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer> + 'a,
+    {
+        self.push(Condition::Frame(self.frame + frames), guarded);
+    }
+
+    fn push<Resource, Destroyer>(
+        &mut self,
+        condition: Condition,
+        guarded: GuardedResource<'a, Resource, Destroyer>,
+    ) where
+        Resource: Destroyable + 'a,
+        Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer> + 'a,
+    {
+        self.entries.push(Entry {
+            condition,
+            _guarded: Box::new(guarded),
+        });
+    }
+
+    /// Destroys all queued resources whose condition has been met.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while querying fence status. Fences aren't queried
+    /// after an error, but resources that were found to be ready are still destroyed.
+    pub fn collect(&mut self) -> VkResult<()> {
+        let mut result = Ok(());
+        let (mut ready, pending) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|entry| match entry.condition {
+                Condition::Frame(frame) => frame <= self.frame,
+                Condition::Fence(_) if result.is_err() => false,
+                Condition::Fence(fence) => unsafe { self.device.get_fence_status(fence) }
+                    .unwrap_or_else(|err| {
+                        result = Err(err);
+                        false
+                    }),
+            });
+        self.entries = pending;
+        drop_last_to_first(&mut ready);
+
+        result
+    }
+
+    /// Advances to the next frame, then calls [`collect`](Self::collect).
+    pub fn next_frame(&mut self) -> VkResult<()> {
+        self.frame += 1;
+        self.collect()
+    }
+
+    /// Returns the number of queued resources.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no resources are queued.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the device used to query fence status.
+    pub fn device(&self) -> &DeviceRef {
+        &self.device
+    }
+}
+
+impl<'a, DeviceRef> Drop for DeletionQueue<'a, DeviceRef>
+where
+    DeviceRef: Deref<Target = ash::Device>,
+{
+    fn drop(&mut self) {
+        drop_last_to_first(&mut self.entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::{mock, DeletionQueue, DeviceExt};
+
+    #[test]
+    fn fence_entries_are_destroyed_in_reverse_once_signaled() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let fence = unsafe { (&device).create_guarded_fence(&Default::default(), None) }.unwrap();
+        let (buffer, image) = unsafe {
+            (
+                (&device)
+                    .create_guarded_buffer(&Default::default(), Some(&allocation_callbacks))
+                    .unwrap(),
+                (&device)
+                    .create_guarded_image(&Default::default(), Some(&allocation_callbacks))
+                    .unwrap(),
+            )
+        };
+        let (buffer_handle, image_handle) = (*buffer, *image);
+        buffer.defer_until(&mut deletion_queue, *fence);
+        image.defer_until(&mut deletion_queue, *fence);
+
+        deletion_queue.collect().unwrap();
+        assert_eq!(deletion_queue.len(), 2);
+
+        mock::signal_fence(*fence);
+        deletion_queue.collect().unwrap();
+        assert!(deletion_queue.is_empty());
+
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[calls.len() - 2..],
+            [
+                mock::Call::new("vkDestroyImage", image_handle, Some(&allocation_callbacks)),
+                mock::Call::new(
+                    "vkDestroyBuffer",
+                    buffer_handle,
+                    Some(&allocation_callbacks)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_entries_are_destroyed_after_frames_pass() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let sampler = unsafe {
+            (&device).create_guarded_sampler(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let sampler_handle = *sampler;
+        sampler.defer_frames(&mut deletion_queue, 2);
+
+        deletion_queue.next_frame().unwrap();
+        assert_eq!(deletion_queue.len(), 1);
+        deletion_queue.next_frame().unwrap();
+        assert!(deletion_queue.is_empty());
+
+        assert_eq!(
+            mock::calls(&device).last(),
+            Some(&mock::Call::new(
+                "vkDestroySampler",
+                sampler_handle,
+                Some(&allocation_callbacks)
+            ))
+        );
+    }
+
+    #[test]
+    fn dropping_queue_destroys_pending_entries() {
+        let (_instance, device) = mock::instance_and_device();
+        let sampler_handle;
+
+        {
+            let mut deletion_queue = DeletionQueue::new(&device);
+            let sampler =
+                unsafe { (&device).create_guarded_sampler(&Default::default(), None) }.unwrap();
+            sampler_handle = *sampler;
+            sampler.defer_until(&mut deletion_queue, vk::Fence::null());
+            deletion_queue.collect().unwrap();
+            assert_eq!(deletion_queue.len(), 1);
+        }
+
+        assert_eq!(
+            mock::calls(&device).last(),
+            Some(&mock::Call::new("vkDestroySampler", sampler_handle, None))
+        );
+    }
+}
@@ -1,8 +1,15 @@
+use std::borrow::BorrowMut;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::BuildHasher;
+
 use ash::{
     extensions::{ext, khr, nv},
-    vk,
+    vk::{self, Handle},
+    RawPtr,
 };
 
+use crate::{DeviceFn, PhysicalDeviceFn, PooledCommandBuffers, PooledDescriptorSets};
+
 /// Indicates that a type is destroyable
 ///
 /// Vulkan resources generally need to be created and destroyed via an [`ash::Device`] or a Vulkan
@@ -54,6 +61,10 @@ use ash::{
 ///     unimplemented!()
 /// }
 /// ```
+///
+/// If destroying one element of a container (e.g. a [`Vec`]) panics, the remaining elements are
+/// still destroyed before the panic continues unwinding. Should another element panic while that
+/// happens, the process aborts rather than leaking the rest.
 pub trait Destroyable {
     /// The type that performs the destruction of the `Destroyable`
     type Destroyer: ?Sized;
@@ -68,6 +79,93 @@ pub trait Destroyable {
         destroyer: &Self::Destroyer,
         allocation_callbacks: Option<&vk::AllocationCallbacks>,
     );
+
+    /// Calls `visitor` with the object type and raw handle of each Vulkan object that
+    /// [`destroy_with`](Self::destroy_with) would destroy.
+    ///
+    /// This is only used by the `leak-tracking` feature; the default implementation reports
+    /// nothing. Handles that are reported should be destroyed via their own [`Destroyable`]
+    /// implementations, otherwise they'll be reported as leaked.
+    fn visit_handles(&self, _visitor: &mut dyn FnMut(vk::ObjectType, u64)) {}
+}
+
+#[cfg(feature = "leak-tracking")]
+fn unregister<H: Handle>(handle: H) {
+    crate::leak::unregister(H::TYPE, handle.as_raw());
+}
+
+#[cfg(not(feature = "leak-tracking"))]
+fn unregister<H: Handle>(_handle: H) {}
+
+fn visit_handle<H: Handle>(handle: H, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+    visitor(H::TYPE, handle.as_raw());
+}
+
+/// Destroys each of `resources` in turn, carrying on with the rest if one of them panics.
+unsafe fn destroy_each<Resource, Items>(
+    resources: Items,
+    destroyer: &Resource::Destroyer,
+    allocation_callbacks: Option<&vk::AllocationCallbacks>,
+) where
+    Resource: Destroyable + ?Sized,
+    Items: Iterator,
+    Items::Item: BorrowMut<Resource>,
+{
+    // Destroys whatever is left when dropped, which only happens early while unwinding from a
+    // panic. A second panic then aborts, which is how Rust treats panicking during unwinding.
+    struct Remaining<'a, Resource, Items>
+    where
+        Resource: Destroyable + ?Sized,
+        Items: Iterator,
+        Items::Item: BorrowMut<Resource>,
+    {
+        resources: Items,
+        destroyer: &'a Resource::Destroyer,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    }
+
+    impl<'a, Resource, Items> Drop for Remaining<'a, Resource, Items>
+    where
+        Resource: Destroyable + ?Sized,
+        Items: Iterator,
+        Items::Item: BorrowMut<Resource>,
+    {
+        fn drop(&mut self) {
+            for mut resource in &mut self.resources {
+                unsafe {
+                    resource
+                        .borrow_mut()
+                        .destroy_with(self.destroyer, self.allocation_callbacks);
+                }
+            }
+        }
+    }
+
+    let mut remaining = Remaining::<Resource, _> {
+        resources,
+        destroyer,
+        allocation_callbacks,
+    };
+    for mut resource in &mut remaining.resources {
+        resource
+            .borrow_mut()
+            .destroy_with(remaining.destroyer, remaining.allocation_callbacks);
+    }
+}
+
+/// Drops `guards` last-to-first, carrying on with the rest if dropping one of them panics.
+pub(crate) fn drop_last_to_first<T>(guards: &mut Vec<T>) {
+    // Like in destroy_each(), this only has anything left to drop while unwinding.
+    struct Remaining<'a, T>(&'a mut Vec<T>);
+
+    impl<'a, T> Drop for Remaining<'a, T> {
+        fn drop(&mut self) {
+            while self.0.pop().is_some() {}
+        }
+    }
+
+    let remaining = Remaining(guards);
+    while remaining.0.pop().is_some() {}
 }
 
 impl Destroyable for ash::Instance {
@@ -79,6 +177,11 @@ impl Destroyable for ash::Instance {
         allocation_callbacks: Option<&vk::AllocationCallbacks>,
     ) {
         self.destroy_instance(allocation_callbacks);
+        unregister(self.handle());
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        visit_handle(self.handle(), visitor);
     }
 }
 
@@ -91,6 +194,11 @@ impl Destroyable for ash::Device {
         allocation_callbacks: Option<&vk::AllocationCallbacks>,
     ) {
         self.destroy_device(allocation_callbacks);
+        unregister(self.handle());
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        visit_handle(self.handle(), visitor);
     }
 }
 
@@ -105,6 +213,11 @@ macro_rules! destroyable {
                 allocation_callbacks: Option<&vk::AllocationCallbacks>,
             ) {
                 device.$destroy(*self, allocation_callbacks);
+                unregister(*self);
+            }
+
+            fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+                visit_handle(*self, visitor);
             }
         }
     };
@@ -137,9 +250,52 @@ destroyable!(
 );
 destroyable!(destroy_sampler_ycbcr_conversion, vk::SamplerYcbcrConversion);
 
-// TODO: Look for ways to implement something vaguely equivalent to:
-//     Destroyable<Destroyer=(&ash::Device, vk::CommandPool)> vk::CommandBuffer
-//     Destroyable<Destroyer=(&ash::Device, vk::DescriptorPool)> vk::DescriptorSet
+impl Destroyable for PooledCommandBuffers {
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        if !self.is_empty() {
+            device.free_command_buffers(self.command_pool(), self);
+        }
+        for &command_buffer in self.iter() {
+            unregister(command_buffer);
+        }
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for &command_buffer in self.iter() {
+            visit_handle(command_buffer, visitor);
+        }
+    }
+}
+
+impl Destroyable for PooledDescriptorSets {
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        if !self.is_empty() {
+            // vkFreeDescriptorSets always returns VK_SUCCESS
+            let _ = device.free_descriptor_sets(self.descriptor_pool(), self);
+        }
+        for &descriptor_set in self.iter() {
+            unregister(descriptor_set);
+        }
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for &descriptor_set in self.iter() {
+            visit_handle(descriptor_set, visitor);
+        }
+    }
+}
 
 macro_rules! destroyable_ext {
     ($Destroyer:ty, $destroy:ident, $Resource:ty) => {
@@ -152,6 +308,11 @@ macro_rules! destroyable_ext {
                 allocation_callbacks: Option<&vk::AllocationCallbacks>,
             ) {
                 destroyer.$destroy(*self, allocation_callbacks);
+                unregister(*self);
+            }
+
+            fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+                visit_handle(*self, visitor);
             }
         }
     };
@@ -180,17 +341,121 @@ destroyable_ext!(
 destroyable_ext!(khr::Surface, destroy_surface, vk::SurfaceKHR);
 destroyable_ext!(khr::Swapchain, destroy_swapchain, vk::SwapchainKHR);
 
-// TODO: Figure out the following:
-//     CuFunctionNVX
-//     CuModuleNVX
-//     DisplayKHR
-//     DisplayModeKHR
-//     IndirectCommandsLayoutNV
-//     PerformanceConfigurationINTEL
-//     PrivateDataSlotEXT
-//     ValidationCacheEXT
-//     VideoSessionKHR
-//     VideoSessionParametersKHR
+destroyable_ext!(
+    ext::PrivateData,
+    destroy_private_data_slot,
+    vk::PrivateDataSlotEXT
+);
+
+// Display modes can't be destroyed; they live as long as the display they were created for. The
+// impl exists so that they can be guarded alongside other resources.
+impl Destroyable for vk::DisplayModeKHR {
+    type Destroyer = khr::Display;
+
+    unsafe fn destroy_with(
+        &mut self,
+        _destroyer: &khr::Display,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        unregister(*self);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        visit_handle(*self, visitor);
+    }
+}
+
+impl Destroyable for vk::DisplayKHR {
+    type Destroyer = PhysicalDeviceFn<vk::ExtDirectModeDisplayFn>;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        // vkReleaseDisplayEXT always returns VK_SUCCESS
+        let _ = (destroyer.fp().release_display_ext)(destroyer.physical_device(), *self);
+        unregister(*self);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        visit_handle(*self, visitor);
+    }
+}
+
+macro_rules! destroyable_raw {
+    ($Fp:ty, $destroy:ident, $Resource:ty) => {
+        impl Destroyable for $Resource {
+            type Destroyer = DeviceFn<$Fp>;
+
+            unsafe fn destroy_with(
+                &mut self,
+                destroyer: &Self::Destroyer,
+                allocation_callbacks: Option<&vk::AllocationCallbacks>,
+            ) {
+                (destroyer.fp().$destroy)(
+                    destroyer.device(),
+                    *self,
+                    allocation_callbacks.as_raw_ptr(),
+                );
+                unregister(*self);
+            }
+
+            fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+                visit_handle(*self, visitor);
+            }
+        }
+    };
+}
+
+destroyable_raw!(
+    vk::NvxBinaryImportFn,
+    destroy_cu_function_nvx,
+    vk::CuFunctionNVX
+);
+destroyable_raw!(
+    vk::NvxBinaryImportFn,
+    destroy_cu_module_nvx,
+    vk::CuModuleNVX
+);
+destroyable_raw!(
+    vk::NvDeviceGeneratedCommandsFn,
+    destroy_indirect_commands_layout_nv,
+    vk::IndirectCommandsLayoutNV
+);
+destroyable_raw!(
+    vk::ExtValidationCacheFn,
+    destroy_validation_cache_ext,
+    vk::ValidationCacheEXT
+);
+destroyable_raw!(
+    vk::KhrVideoQueueFn,
+    destroy_video_session_khr,
+    vk::VideoSessionKHR
+);
+destroyable_raw!(
+    vk::KhrVideoQueueFn,
+    destroy_video_session_parameters_khr,
+    vk::VideoSessionParametersKHR
+);
+
+impl Destroyable for vk::PerformanceConfigurationINTEL {
+    type Destroyer = DeviceFn<vk::IntelPerformanceQueryFn>;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        // There's nothing useful to do if releasing fails
+        let _ = (destroyer.fp().release_performance_configuration_intel)(destroyer.device(), *self);
+        unregister(*self);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        visit_handle(*self, visitor);
+    }
+}
 
 impl<Resource: Destroyable> Destroyable for Vec<Resource> {
     type Destroyer = <Resource as Destroyable>::Destroyer;
@@ -200,8 +465,12 @@ impl<Resource: Destroyable> Destroyable for Vec<Resource> {
         destroyer: &Self::Destroyer,
         allocation_callbacks: Option<&vk::AllocationCallbacks>,
     ) {
-        for mut resource in self.drain(..) {
-            resource.destroy_with(destroyer, allocation_callbacks);
+        destroy_each::<Resource, _>(self.drain(..), destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self {
+            resource.visit_handles(visitor);
         }
     }
 }
@@ -214,8 +483,12 @@ impl<Resource: Destroyable, const N: usize> Destroyable for [Resource; N] {
         destroyer: &Self::Destroyer,
         allocation_callbacks: Option<&vk::AllocationCallbacks>,
     ) {
+        destroy_each::<Resource, _>(self.iter_mut(), destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
         for resource in self {
-            resource.destroy_with(destroyer, allocation_callbacks);
+            resource.visit_handles(visitor);
         }
     }
 }
@@ -232,6 +505,12 @@ impl<Resource: Destroyable> Destroyable for Option<Resource> {
             resource.destroy_with(destroyer, allocation_callbacks);
         }
     }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        if let Some(resource) = self {
+            resource.visit_handles(visitor);
+        }
+    }
 }
 
 impl<Resource: Destroyable, Error> Destroyable for Result<Resource, Error> {
@@ -246,4 +525,223 @@ impl<Resource: Destroyable, Error> Destroyable for Result<Resource, Error> {
             resource.destroy_with(destroyer, allocation_callbacks);
         }
     }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        if let Ok(resource) = self {
+            resource.visit_handles(visitor);
+        }
+    }
+}
+
+impl<Resource: Destroyable + ?Sized> Destroyable for Box<Resource> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        (**self).destroy_with(destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        (**self).visit_handles(visitor);
+    }
+}
+
+impl<Resource: Destroyable> Destroyable for VecDeque<Resource> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        destroy_each::<Resource, _>(self.drain(..), destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self {
+            resource.visit_handles(visitor);
+        }
+    }
+}
+
+// Maps destroy their values in iteration order; keys are just dropped.
+impl<Key, Resource: Destroyable, S: BuildHasher> Destroyable for HashMap<Key, Resource, S> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        destroy_each::<Resource, _>(
+            self.drain().map(|(_, resource)| resource),
+            destroyer,
+            allocation_callbacks,
+        );
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self.values() {
+            resource.visit_handles(visitor);
+        }
+    }
+}
+
+impl<Key, Resource: Destroyable> Destroyable for BTreeMap<Key, Resource> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        destroy_each::<Resource, _>(
+            std::mem::take(self).into_values(),
+            destroyer,
+            allocation_callbacks,
+        );
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self.values() {
+            resource.visit_handles(visitor);
+        }
+    }
+}
+
+/// Implements [`Destroyable`] for a tuple whose elements share the first one's `Destroyer`,
+/// destroying them last-to-first. Takes the element types followed by their indices in reverse.
+macro_rules! destroyable_tuple {
+    ($First:ident $($Rest:ident)*; $($reversed:tt)+) => {
+        impl<$First, $($Rest,)*> Destroyable for ($First, $($Rest,)*)
+        where
+            $First: Destroyable,
+            $($Rest: Destroyable<Destroyer = <$First as Destroyable>::Destroyer>,)*
+        {
+            type Destroyer = <$First as Destroyable>::Destroyer;
+
+            unsafe fn destroy_with(
+                &mut self,
+                destroyer: &Self::Destroyer,
+                allocation_callbacks: Option<&vk::AllocationCallbacks>,
+            ) {
+                let resources = [
+                    $(&mut self.$reversed as &mut dyn Destroyable<Destroyer = Self::Destroyer>,)+
+                ];
+                destroy_each::<dyn Destroyable<Destroyer = Self::Destroyer>, _>(
+                    resources.into_iter(),
+                    destroyer,
+                    allocation_callbacks,
+                );
+            }
+
+            fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+                $(self.$reversed.visit_handles(visitor);)+
+            }
+        }
+    };
+}
+
+destroyable_tuple!(A; 0);
+destroyable_tuple!(A B; 1 0);
+destroyable_tuple!(A B C; 2 1 0);
+destroyable_tuple!(A B C D; 3 2 1 0);
+destroyable_tuple!(A B C D E; 4 3 2 1 0);
+destroyable_tuple!(A B C D E F; 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G; 6 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G H; 7 6 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G H I; 8 7 6 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G H I J; 9 8 7 6 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G H I J K; 10 9 8 7 6 5 4 3 2 1 0);
+destroyable_tuple!(A B C D E F G H I J K L; 11 10 9 8 7 6 5 4 3 2 1 0);
+
+/// Container adapter that destroys its elements last-to-first
+///
+/// The [`Destroyable`] impls for [`Vec`] and arrays destroy elements first-to-last, but objects
+/// that were created from earlier ones (e.g. pipelines derived from a base pipeline) usually need
+/// to be destroyed in reverse. Wrapping the container in [`Reversed`] does that:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{Guarded, GuardedResource, Reversed};
+///
+/// unsafe fn create_pipelines<'a>(
+///     device: &'a ash::Device,
+///     create_infos: &[vk::GraphicsPipelineCreateInfo],
+/// ) -> VkResult<Guarded<'a, Reversed<Vec<vk::Pipeline>>>> {
+///     // If creating a pipeline fails, the ones before it are destroyed last-to-first
+///     GuardedResource::try_new_reversed_from(
+///         create_infos.iter().map(|create_info| create_pipeline(device, create_info)),
+///         device,
+///         None,
+///     )
+/// }
+/// # unsafe fn create_pipeline(
+/// #     _: &ash::Device,
+/// #     _: &vk::GraphicsPipelineCreateInfo,
+/// # ) -> VkResult<vk::Pipeline> {
+/// #     unimplemented!()
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Reversed<Container>(pub Container);
+
+impl<Container> Reversed<Container> {
+    /// Returns the wrapped container.
+    pub fn into_inner(self) -> Container {
+        self.0
+    }
+}
+
+impl<Container> std::ops::Deref for Reversed<Container> {
+    type Target = Container;
+
+    fn deref(&self) -> &Container {
+        &self.0
+    }
+}
+
+impl<Container> std::ops::DerefMut for Reversed<Container> {
+    fn deref_mut(&mut self) -> &mut Container {
+        &mut self.0
+    }
+}
+
+impl<Resource: Destroyable> Destroyable for Reversed<Vec<Resource>> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        destroy_each::<Resource, _>(self.0.drain(..).rev(), destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self.0.iter().rev() {
+            resource.visit_handles(visitor);
+        }
+    }
+}
+
+impl<Resource: Destroyable, const N: usize> Destroyable for Reversed<[Resource; N]> {
+    type Destroyer = <Resource as Destroyable>::Destroyer;
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &Self::Destroyer,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        destroy_each::<Resource, _>(self.0.iter_mut().rev(), destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        for resource in self.0.iter().rev() {
+            resource.visit_handles(visitor);
+        }
+    }
 }
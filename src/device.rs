@@ -1,11 +1,16 @@
+use std::ffi::CStr;
 use std::ops::Deref;
 
-use ash::{prelude::VkResult, vk};
+use ash::{extensions::ext, prelude::VkResult, vk};
 
-use crate::GuardedResource;
+use crate::bound::allocate_guarded_memory_for;
+use crate::{
+    BoundBuffer, BoundImage, Destroyable, GuardedResource, MemoryTypeSelector, PipelineCacheFile,
+    PooledCommandBuffers, PooledDescriptorSets,
+};
 
 macro_rules! declaration {
-    ($name:ident, $create:expr, $CreateInfo:ty, $Resource:ty,) => {
+    ($name:ident, $named:ident, $create:expr, $CreateInfo:ty, $Resource:ty,) => {
         #[doc = concat!(
             "Same as [`", stringify!($create), "`](ash::Device::", stringify!($create), ") but ",
             "returns guarded [`", stringify!($Resource), "`]."
@@ -19,7 +24,7 @@ macro_rules! declaration {
 }
 
 macro_rules! definition {
-    ($name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+    ($name:ident, $named:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
         unsafe fn $name<'a>(
             &self,
             create_info: &$CreateInfo,
@@ -35,10 +40,44 @@ macro_rules! definition {
     };
 }
 
+macro_rules! named_declaration {
+    ($name:ident, $named:ident, $create:expr, $CreateInfo:ty, $Resource:ty,) => {
+        #[doc = concat!(
+            "Same as [`", stringify!($name), "`](Self::", stringify!($name), ") but also names ",
+            "the [`", stringify!($Resource), "`] via ",
+            "[`set_debug_utils_object_names`](Self::set_debug_utils_object_names)."
+        )]
+        unsafe fn $named<'a>(
+            &self,
+            debug_utils: &ext::DebugUtils,
+            name: &CStr,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>>;
+    };
+}
+
+macro_rules! named_definition {
+    ($name:ident, $named:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+        unsafe fn $named<'a>(
+            &self,
+            debug_utils: &ext::DebugUtils,
+            name: &CStr,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>> {
+            let resource = self.$name(create_info, allocation_callbacks)?;
+            self.set_debug_utils_object_names(debug_utils, &*resource, name)?;
+            Ok(resource)
+        }
+    };
+}
+
 macro_rules! device_methods {
     ($method:ident) => {
         $method!(
             allocate_guarded_memory,
+            allocate_named_memory,
             allocate_memory,
             vk::MemoryAllocateInfo,
             vk::DeviceMemory,
@@ -46,6 +85,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_buffer,
+            create_named_buffer,
             create_buffer,
             vk::BufferCreateInfo,
             vk::Buffer,
@@ -53,6 +93,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_buffer_view,
+            create_named_buffer_view,
             create_buffer_view,
             vk::BufferViewCreateInfo,
             vk::BufferView,
@@ -60,6 +101,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_command_pool,
+            create_named_command_pool,
             create_command_pool,
             vk::CommandPoolCreateInfo,
             vk::CommandPool,
@@ -67,6 +109,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_descriptor_pool,
+            create_named_descriptor_pool,
             create_descriptor_pool,
             vk::DescriptorPoolCreateInfo,
             vk::DescriptorPool,
@@ -74,6 +117,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_descriptor_set_layout,
+            create_named_descriptor_set_layout,
             create_descriptor_set_layout,
             vk::DescriptorSetLayoutCreateInfo,
             vk::DescriptorSetLayout,
@@ -81,6 +125,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_event,
+            create_named_event,
             create_event,
             vk::EventCreateInfo,
             vk::Event,
@@ -88,6 +133,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_fence,
+            create_named_fence,
             create_fence,
             vk::FenceCreateInfo,
             vk::Fence,
@@ -95,6 +141,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_framebuffer,
+            create_named_framebuffer,
             create_framebuffer,
             vk::FramebufferCreateInfo,
             vk::Framebuffer,
@@ -102,6 +149,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_image,
+            create_named_image,
             create_image,
             vk::ImageCreateInfo,
             vk::Image,
@@ -109,6 +157,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_image_view,
+            create_named_image_view,
             create_image_view,
             vk::ImageViewCreateInfo,
             vk::ImageView,
@@ -116,6 +165,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_pipeline_layout,
+            create_named_pipeline_layout,
             create_pipeline_layout,
             vk::PipelineLayoutCreateInfo,
             vk::PipelineLayout,
@@ -123,6 +173,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_pipeline_cache,
+            create_named_pipeline_cache,
             create_pipeline_cache,
             vk::PipelineCacheCreateInfo,
             vk::PipelineCache,
@@ -130,6 +181,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_query_pool,
+            create_named_query_pool,
             create_query_pool,
             vk::QueryPoolCreateInfo,
             vk::QueryPool,
@@ -137,6 +189,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_render_pass,
+            create_named_render_pass,
             create_render_pass,
             vk::RenderPassCreateInfo,
             vk::RenderPass,
@@ -144,6 +197,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_sampler,
+            create_named_sampler,
             create_sampler,
             vk::SamplerCreateInfo,
             vk::Sampler,
@@ -151,6 +205,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_semaphore,
+            create_named_semaphore,
             create_semaphore,
             vk::SemaphoreCreateInfo,
             vk::Semaphore,
@@ -158,6 +213,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_shader_module,
+            create_named_shader_module,
             create_shader_module,
             vk::ShaderModuleCreateInfo,
             vk::ShaderModule,
@@ -167,6 +223,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_descriptor_update_template,
+            create_named_descriptor_update_template,
             create_descriptor_update_template,
             vk::DescriptorUpdateTemplateCreateInfo,
             vk::DescriptorUpdateTemplate,
@@ -174,6 +231,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_sampler_ycbcr_conversion,
+            create_named_sampler_ycbcr_conversion,
             create_sampler_ycbcr_conversion,
             vk::SamplerYcbcrConversionCreateInfo,
             vk::SamplerYcbcrConversion,
@@ -183,6 +241,7 @@ macro_rules! device_methods {
 
         $method!(
             create_guarded_render_pass2,
+            create_named_render_pass2,
             create_render_pass2,
             vk::RenderPassCreateInfo2,
             vk::RenderPass,
@@ -190,12 +249,26 @@ macro_rules! device_methods {
     };
 }
 
-type PipelinesResult<T> = Result<T, (T, vk::Result)>;
+pub(crate) type PipelinesResult<T> = Result<T, (T, vk::Result)>;
 
 /// Extension trait adding guarded methods to [`ash::Device`]
 #[allow(clippy::missing_safety_doc)]
 pub trait DeviceExt: Sized + Deref<Target = ash::Device> {
     device_methods!(declaration);
+    device_methods!(named_declaration);
+
+    /// Names every Vulkan object in `resource` (as reported by
+    /// [`Destroyable::visit_handles`]) `name` via
+    /// [`set_debug_utils_object_name`](ext::DebugUtils::set_debug_utils_object_name), mapping
+    /// handle types to [`vk::ObjectType`] automatically.
+    ///
+    /// Types that don't report their handles are left unnamed.
+    unsafe fn set_debug_utils_object_names(
+        &self,
+        debug_utils: &ext::DebugUtils,
+        resource: &impl Destroyable,
+        name: &CStr,
+    ) -> VkResult<()>;
 
     /// Same as [`create_graphics_pipelines`](ash::Device::create_graphics_pipelines) but returns
     /// guarded [`vk::Pipeline`]s.
@@ -215,8 +288,75 @@ pub trait DeviceExt: Sized + Deref<Target = ash::Device> {
         allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
     ) -> PipelinesResult<GuardedResource<'a, Vec<vk::Pipeline>, Self>>;
 
-    // TODO: allocate_guarded_command_buffers
-    // TODO: allocate_guarded_descriptor_sets
+    /// Same as [`allocate_command_buffers`](ash::Device::allocate_command_buffers) but returns
+    /// guarded [`PooledCommandBuffers`] that are freed to `allocate_info.command_pool`.
+    unsafe fn allocate_guarded_command_buffers(
+        &self,
+        allocate_info: &vk::CommandBufferAllocateInfo,
+    ) -> VkResult<GuardedResource<'static, PooledCommandBuffers, Self>>;
+
+    /// Same as [`allocate_descriptor_sets`](ash::Device::allocate_descriptor_sets) but returns
+    /// guarded [`PooledDescriptorSets`] that are freed to `allocate_info.descriptor_pool`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`allocate_descriptor_sets`](ash::Device::allocate_descriptor_sets). In addition,
+    /// `pool_flags` must be the flags that `allocate_info.descriptor_pool` was created with.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`vk::Result::ERROR_VALIDATION_FAILED_EXT`] without allocating anything if
+    /// `pool_flags` doesn't contain
+    /// [`FREE_DESCRIPTOR_SET`](vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET), because
+    /// freeing descriptor sets to such pools is invalid. Otherwise returns the error from
+    /// [`allocate_descriptor_sets`](ash::Device::allocate_descriptor_sets).
+    unsafe fn allocate_guarded_descriptor_sets(
+        &self,
+        allocate_info: &vk::DescriptorSetAllocateInfo,
+        pool_flags: vk::DescriptorPoolCreateFlags,
+    ) -> VkResult<GuardedResource<'static, PooledDescriptorSets, Self>>;
+
+    /// Creates a buffer, allocates memory for it from the type picked by `memory_types` and binds
+    /// the two together, returning them guarded as a [`BoundBuffer`]. Whatever was created is
+    /// destroyed if a later step fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`vk::Result::ERROR_OUT_OF_DEVICE_MEMORY`] if no memory type is suitable, as if
+    /// allocating from the required kind of memory had failed, so that callers falling back to
+    /// other memory types on allocation failure handle both cases alike. Use
+    /// [`MemoryTypeSelector::select`] up front to tell them apart. Otherwise, returns the first
+    /// error from creating, allocating or binding.
+    unsafe fn create_guarded_bound_buffer<'a>(
+        &self,
+        create_info: &vk::BufferCreateInfo,
+        memory_types: &MemoryTypeSelector,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, BoundBuffer, Self>>;
+
+    /// Same as [`create_guarded_bound_buffer`](Self::create_guarded_bound_buffer), but for an
+    /// image, returned as a [`BoundImage`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`create_guarded_bound_buffer`](Self::create_guarded_bound_buffer).
+    unsafe fn create_guarded_bound_image<'a>(
+        &self,
+        create_info: &vk::ImageCreateInfo,
+        memory_types: &MemoryTypeSelector,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, BoundImage, Self>>;
+
+    /// Same as [`create_guarded_pipeline_cache`](Self::create_guarded_pipeline_cache), but the
+    /// initial data is [loaded](PipelineCacheFile::load) from `file`. The pipeline cache starts
+    /// empty if the file is missing, incompatible or can't be read, since pipeline caches are
+    /// only an optimization.
+    unsafe fn create_guarded_pipeline_cache_from_file<'a>(
+        &self,
+        file: &PipelineCacheFile,
+        flags: vk::PipelineCacheCreateFlags,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::PipelineCache, Self>>;
 }
 
 impl<DeviceRef> DeviceExt for DeviceRef
@@ -224,6 +364,26 @@ where
     DeviceRef: Clone + Deref<Target = ash::Device>,
 {
     device_methods!(definition);
+    device_methods!(named_definition);
+
+    unsafe fn set_debug_utils_object_names(
+        &self,
+        debug_utils: &ext::DebugUtils,
+        resource: &impl Destroyable,
+        name: &CStr,
+    ) -> VkResult<()> {
+        let mut result = Ok(());
+        resource.visit_handles(&mut |object_type, object_handle| {
+            if result.is_ok() {
+                let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+                    .object_type(object_type)
+                    .object_handle(object_handle)
+                    .object_name(name);
+                result = debug_utils.set_debug_utils_object_name(self.handle(), &name_info);
+            }
+        });
+        result
+    }
 
     unsafe fn create_guarded_graphics_pipelines<'a>(
         &self,
@@ -250,4 +410,311 @@ where
             .map(guard)
             .map_err(|(pipelines, result)| (guard(pipelines), result))
     }
+
+    unsafe fn allocate_guarded_command_buffers(
+        &self,
+        allocate_info: &vk::CommandBufferAllocateInfo,
+    ) -> VkResult<GuardedResource<'static, PooledCommandBuffers, Self>> {
+        let command_buffers = self.allocate_command_buffers(allocate_info)?;
+        let command_buffers =
+            PooledCommandBuffers::new(allocate_info.command_pool, command_buffers);
+        Ok(GuardedResource::new(command_buffers, self.clone(), None))
+    }
+
+    unsafe fn allocate_guarded_descriptor_sets(
+        &self,
+        allocate_info: &vk::DescriptorSetAllocateInfo,
+        pool_flags: vk::DescriptorPoolCreateFlags,
+    ) -> VkResult<GuardedResource<'static, PooledDescriptorSets, Self>> {
+        if !pool_flags.contains(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET) {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        let descriptor_sets = self.allocate_descriptor_sets(allocate_info)?;
+        let descriptor_sets =
+            PooledDescriptorSets::new(allocate_info.descriptor_pool, pool_flags, descriptor_sets);
+        Ok(GuardedResource::new(descriptor_sets, self.clone(), None))
+    }
+
+    unsafe fn create_guarded_bound_buffer<'a>(
+        &self,
+        create_info: &vk::BufferCreateInfo,
+        memory_types: &MemoryTypeSelector,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, BoundBuffer, Self>> {
+        let buffer = self.create_guarded_buffer(create_info, allocation_callbacks)?;
+        let requirements = self.get_buffer_memory_requirements(*buffer);
+        let memory =
+            allocate_guarded_memory_for(self, &requirements, memory_types, allocation_callbacks)?;
+        self.bind_buffer_memory(*buffer, *memory, 0)?;
+        let bound = BoundBuffer {
+            buffer: buffer.take(),
+            memory: memory.take(),
+        };
+        Ok(GuardedResource::new(
+            bound,
+            self.clone(),
+            allocation_callbacks,
+        ))
+    }
+
+    unsafe fn create_guarded_bound_image<'a>(
+        &self,
+        create_info: &vk::ImageCreateInfo,
+        memory_types: &MemoryTypeSelector,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, BoundImage, Self>> {
+        let image = self.create_guarded_image(create_info, allocation_callbacks)?;
+        let requirements = self.get_image_memory_requirements(*image);
+        let memory =
+            allocate_guarded_memory_for(self, &requirements, memory_types, allocation_callbacks)?;
+        self.bind_image_memory(*image, *memory, 0)?;
+        let bound = BoundImage {
+            image: image.take(),
+            memory: memory.take(),
+        };
+        Ok(GuardedResource::new(
+            bound,
+            self.clone(),
+            allocation_callbacks,
+        ))
+    }
+
+    unsafe fn create_guarded_pipeline_cache_from_file<'a>(
+        &self,
+        file: &PipelineCacheFile,
+        flags: vk::PipelineCacheCreateFlags,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::PipelineCache, Self>> {
+        let data = file.load().unwrap_or_default();
+        let create_info = vk::PipelineCacheCreateInfo::builder()
+            .flags(flags)
+            .initial_data(&data);
+        self.create_guarded_pipeline_cache(&create_info, allocation_callbacks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::{
+        extensions::ext,
+        vk::{self, Handle},
+    };
+
+    use crate::{mock, DeviceExt};
+
+    fn assert_created_and_destroyed(
+        calls: &[mock::Call],
+        handle: u64,
+        allocation_callbacks: &vk::AllocationCallbacks,
+    ) {
+        let allocation_callbacks = Some(allocation_callbacks as *const _);
+        assert_eq!(calls.len(), 2, "unexpected calls: {calls:?}");
+        assert!(
+            calls[0].function.starts_with("vkCreate") || calls[0].function == "vkAllocateMemory"
+        );
+        assert!(calls[1].function.starts_with("vkDestroy") || calls[1].function == "vkFreeMemory");
+        for call in calls {
+            assert_eq!(call.handle, handle);
+            assert_eq!(call.allocation_callbacks, allocation_callbacks);
+        }
+    }
+
+    macro_rules! test {
+        ($name:ident, $named:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+            #[test]
+            fn $name() {
+                let (_instance, device) = mock::instance_and_device();
+                let allocation_callbacks = vk::AllocationCallbacks::default();
+
+                let resource = unsafe {
+                    (&device).$name(&<$CreateInfo>::default(), Some(&allocation_callbacks))
+                }
+                .unwrap();
+                let handle = resource.as_raw();
+                drop(resource);
+
+                let calls = mock::calls(&device);
+                assert_created_and_destroyed(&calls[1..], handle, &allocation_callbacks);
+            }
+        };
+    }
+
+    device_methods!(test);
+
+    #[test]
+    fn create_named_buffer() {
+        let (instance, device) = mock::instance_and_device();
+        let debug_utils = ext::DebugUtils::new(&mock::entry(), &instance);
+        let name = c"vertices";
+
+        let buffer =
+            unsafe { (&device).create_named_buffer(&debug_utils, name, &Default::default(), None) }
+                .unwrap();
+        let handle = *buffer;
+        drop(buffer);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateBuffer", handle, None),
+                mock::Call::named("vkSetDebugUtilsObjectNameEXT", handle, "vertices"),
+                mock::Call::new("vkDestroyBuffer", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_debug_utils_object_names_names_every_handle() {
+        let (instance, device) = mock::instance_and_device();
+        let debug_utils = ext::DebugUtils::new(&mock::entry(), &instance);
+        let name = c"frame";
+        let fences = [vk::Fence::from_raw(1), vk::Fence::from_raw(2)];
+
+        unsafe { (&device).set_debug_utils_object_names(&debug_utils, &fences, name) }.unwrap();
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::named("vkSetDebugUtilsObjectNameEXT", fences[0], "frame"),
+                mock::Call::named("vkSetDebugUtilsObjectNameEXT", fences[1], "frame"),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_guarded_graphics_pipelines() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let create_infos = [vk::GraphicsPipelineCreateInfo::default(); 2];
+
+        let pipelines = unsafe {
+            (&device).create_guarded_graphics_pipelines(
+                vk::PipelineCache::null(),
+                &create_infos,
+                Some(&allocation_callbacks),
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap();
+        let handles = pipelines.clone();
+        drop(pipelines);
+
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[1..],
+            [
+                mock::Call::new(
+                    "vkCreateGraphicsPipelines",
+                    handles[0],
+                    Some(&allocation_callbacks)
+                ),
+                mock::Call::new(
+                    "vkCreateGraphicsPipelines",
+                    handles[1],
+                    Some(&allocation_callbacks)
+                ),
+                mock::Call::new("vkDestroyPipeline", handles[0], Some(&allocation_callbacks)),
+                mock::Call::new("vkDestroyPipeline", handles[1], Some(&allocation_callbacks)),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_guarded_compute_pipelines() {
+        let (_instance, device) = mock::instance_and_device();
+        let create_infos = [vk::ComputePipelineCreateInfo::default()];
+
+        let pipelines = unsafe {
+            (&device).create_guarded_compute_pipelines(
+                vk::PipelineCache::null(),
+                &create_infos,
+                None,
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap();
+        let handles = pipelines.clone();
+        drop(pipelines);
+
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[1..],
+            [
+                mock::Call::new("vkCreateComputePipelines", handles[0], None),
+                mock::Call::new("vkDestroyPipeline", handles[0], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn allocate_guarded_command_buffers() {
+        let (_instance, device) = mock::instance_and_device();
+        let command_pool =
+            unsafe { device.create_command_pool(&Default::default(), None) }.unwrap();
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .command_buffer_count(2);
+
+        let command_buffers =
+            unsafe { (&device).allocate_guarded_command_buffers(&allocate_info) }.unwrap();
+        assert_eq!(command_buffers.command_pool(), command_pool);
+        let handles = command_buffers.to_vec();
+        drop(command_buffers);
+
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[2..],
+            [
+                mock::Call::pooled("vkAllocateCommandBuffers", handles[0], command_pool),
+                mock::Call::pooled("vkAllocateCommandBuffers", handles[1], command_pool),
+                mock::Call::pooled("vkFreeCommandBuffers", handles[0], command_pool),
+                mock::Call::pooled("vkFreeCommandBuffers", handles[1], command_pool),
+            ]
+        );
+    }
+
+    #[test]
+    fn allocate_guarded_descriptor_sets() {
+        let (_instance, device) = mock::instance_and_device();
+        let pool_flags = vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        let pool_info = vk::DescriptorPoolCreateInfo::builder().flags(pool_flags);
+        let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None) }.unwrap();
+        let set_layouts = [vk::DescriptorSetLayout::null()];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts);
+
+        let descriptor_sets =
+            unsafe { (&device).allocate_guarded_descriptor_sets(&allocate_info, pool_flags) }
+                .unwrap();
+        assert_eq!(descriptor_sets.descriptor_pool(), descriptor_pool);
+        let handles = descriptor_sets.to_vec();
+        drop(descriptor_sets);
+
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[2..],
+            [
+                mock::Call::pooled("vkAllocateDescriptorSets", handles[0], descriptor_pool),
+                mock::Call::pooled("vkFreeDescriptorSets", handles[0], descriptor_pool),
+            ]
+        );
+    }
+
+    #[test]
+    fn descriptor_sets_are_not_allocated_from_pools_without_free_descriptor_set() {
+        let (_instance, device) = mock::instance_and_device();
+        let descriptor_pool =
+            unsafe { device.create_descriptor_pool(&Default::default(), None) }.unwrap();
+        let set_layouts = [vk::DescriptorSetLayout::null()];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts);
+
+        let result = unsafe {
+            (&device).allocate_guarded_descriptor_sets(&allocate_info, Default::default())
+        };
+        assert_eq!(result.err(), Some(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        assert_eq!(mock::calls(&device).len(), 2);
+    }
 }
@@ -0,0 +1,153 @@
+use std::fmt;
+
+use ash::vk;
+
+use crate::{Destroyable, GuardedResource};
+
+/// Object-safe counterpart of [`Destroyable`] for resources that carry their own destroyer
+///
+/// [`Destroyable::destroy_with`] takes the destroyer as an argument, so resources with different
+/// destroyers can't share a trait object. A [`DynDestroyable`] holds on to its destroyer and
+/// allocation callbacks instead. It's implemented by the boxed contents of [`DynGuarded`], and can
+/// be implemented for application-specific types that already own what they need to destroy
+/// themselves.
+pub trait DynDestroyable {
+    /// Destroys `self` with its own destroyer and allocation callbacks.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Destroyable::destroy_with`].
+    unsafe fn destroy(&mut self);
+
+    /// Same as [`Destroyable::visit_handles`].
+    fn visit_handles(&self, _visitor: &mut dyn FnMut(vk::ObjectType, u64)) {}
+}
+
+impl<'a> Destroyable for dyn DynDestroyable + 'a {
+    type Destroyer = ();
+
+    unsafe fn destroy_with(
+        &mut self,
+        _destroyer: &(),
+        _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        self.destroy();
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        DynDestroyable::visit_handles(self, visitor);
+    }
+}
+
+impl<'a> fmt::Debug for dyn DynDestroyable + 'a {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut handles = Vec::new();
+        DynDestroyable::visit_handles(self, &mut |object_type, handle| {
+            handles.push((object_type, handle));
+        });
+        f.debug_tuple("DynDestroyable").field(&handles).finish()
+    }
+}
+
+/// Type-erased [`GuardedResource`]
+///
+/// Guards of different resource and destroyer types can be converted with
+/// [`GuardedResource::into_dyn`], which boxes the resource together with its destroyer and
+/// allocation callbacks. That lets unrelated resources live in the same collection and still be
+/// destroyed correctly:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use ash::{extensions::khr, prelude::VkResult, vk};
+/// use ashpan::{DeviceExt, DynGuarded, GuardedResource, SwapchainExt};
+///
+/// unsafe fn create_resources(
+///     device: Arc<ash::Device>,
+///     swapchain_fn: &khr::Swapchain,
+/// ) -> VkResult<Vec<DynGuarded>> {
+///     let buffer = device.create_buffer(unimplemented!(), None)?;
+///     let buffer = GuardedResource::new(buffer, device.clone(), None);
+///     let sampler = device.create_sampler(unimplemented!(), None)?;
+///     let sampler = GuardedResource::new(sampler, device, None);
+///     let swapchain = swapchain_fn.create_guarded_swapchain(unimplemented!(), None)?;
+///
+///     // Dropping the Vec destroys the resources first-to-last
+///     Ok(vec![
+///         buffer.into_dyn(),
+///         sampler.into_dyn(),
+///         swapchain.into_dyn(),
+///     ])
+/// }
+/// ```
+///
+/// `'a` bounds the lifetimes of the erased resource, destroyer and allocation callbacks.
+/// [`.take()`](GuardedResource::take) extracts the boxed [`DynDestroyable`] without destroying it.
+pub type DynGuarded<'a> = GuardedResource<'static, Box<dyn DynDestroyable + 'a>, &'static ()>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ash::extensions::khr;
+
+    use crate::{mock, DeviceExt, DynGuarded, GuardedResource, SwapchainExt};
+
+    #[test]
+    fn heterogeneous_resources_are_destroyed_by_their_own_destroyers() {
+        let (instance, device) = mock::instance_and_device();
+        let swapchain_fn = khr::Swapchain::new(&instance, &device);
+        let allocation_callbacks = Default::default();
+        let (buffer, sampler, swapchain);
+
+        {
+            let mut guards: Vec<DynGuarded> = Vec::new();
+            unsafe {
+                let guarded_buffer = (&device)
+                    .create_guarded_buffer(&Default::default(), Some(&allocation_callbacks))
+                    .unwrap();
+                buffer = *guarded_buffer;
+                guards.push(guarded_buffer.into_dyn());
+
+                let shared_device = Arc::new(device.clone());
+                let guarded_sampler = GuardedResource::new(
+                    shared_device
+                        .create_sampler(&Default::default(), None)
+                        .unwrap(),
+                    shared_device,
+                    None,
+                );
+                sampler = *guarded_sampler;
+                guards.push(guarded_sampler.into_dyn());
+
+                let guarded_swapchain = (&swapchain_fn)
+                    .create_guarded_swapchain(&Default::default(), None)
+                    .unwrap();
+                swapchain = *guarded_swapchain;
+                guards.push(guarded_swapchain.into_dyn());
+            }
+            assert_eq!(guards.len(), 3);
+        }
+
+        assert_eq!(
+            mock::calls(&device)[4..],
+            [
+                mock::Call::new("vkDestroyBuffer", buffer, Some(&allocation_callbacks)),
+                mock::Call::new("vkDestroySampler", sampler, None),
+                mock::Call::new("vkDestroySwapchainKHR", swapchain, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn taken_resources_are_not_destroyed() {
+        let (_instance, device) = mock::instance_and_device();
+        let guarded = unsafe { (&device).create_guarded_fence(&Default::default(), None) }
+            .unwrap()
+            .into_dyn();
+
+        let mut taken = guarded.take();
+        assert_eq!(mock::calls(&device).len(), 2);
+        unsafe { taken.destroy() };
+        assert_eq!(mock::calls(&device).len(), 3);
+    }
+}
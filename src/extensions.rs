@@ -0,0 +1,914 @@
+use std::ops::Deref;
+
+use ash::{
+    extensions::{ext, khr, mvk, nn, nv},
+    prelude::VkResult,
+    vk, RawPtr,
+};
+
+use crate::{DeviceFn, GuardedResource, PhysicalDeviceFn};
+
+macro_rules! declaration {
+    ($Loader:ty, $name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+        #[doc = concat!(
+            "Same as [`", stringify!($create), "`](ash::extensions::", stringify!($Loader), "::",
+            stringify!($create), ") but returns guarded [`", stringify!($Resource), "`]."
+        )]
+        unsafe fn $name<'a>(
+            &self,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>>;
+    };
+}
+
+macro_rules! definition {
+    ($Loader:ty, $name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+        unsafe fn $name<'a>(
+            &self,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>> {
+            let resource = self.$create(create_info, allocation_callbacks)?;
+            Ok(GuardedResource::new(
+                resource,
+                self.clone(),
+                allocation_callbacks,
+            ))
+        }
+    };
+}
+
+macro_rules! extension_trait {
+    (
+        $(#[$attr:meta])*
+        $Trait:ident for $Loader:ty {
+            $($name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[allow(clippy::missing_safety_doc)]
+        pub trait $Trait: Sized + Deref<Target = $Loader> {
+            $(declaration!($Loader, $name, $create, $CreateInfo, $Resource,);)*
+        }
+
+        impl<LoaderRef> $Trait for LoaderRef
+        where
+            LoaderRef: Clone + Deref<Target = $Loader>,
+        {
+            $(definition!($Loader, $name, $create, $CreateInfo, $Resource,);)*
+        }
+    };
+}
+
+extension_trait! {
+    /// Extension trait adding guarded methods to [`khr::AccelerationStructure`]
+    AccelerationStructureExt for khr::AccelerationStructure {
+        create_guarded_acceleration_structure,
+        create_acceleration_structure,
+        vk::AccelerationStructureCreateInfoKHR,
+        vk::AccelerationStructureKHR,
+    }
+}
+
+extension_trait! {
+    /// Extension trait adding guarded methods to [`ext::DebugUtils`]
+    DebugUtilsExt for ext::DebugUtils {
+        create_guarded_debug_utils_messenger,
+        create_debug_utils_messenger,
+        vk::DebugUtilsMessengerCreateInfoEXT,
+        vk::DebugUtilsMessengerEXT,
+    }
+}
+
+extension_trait! {
+    /// Extension trait adding guarded methods to [`ext::PrivateData`]
+    PrivateDataExt for ext::PrivateData {
+        create_guarded_private_data_slot,
+        create_private_data_slot,
+        vk::PrivateDataSlotCreateInfoEXT,
+        vk::PrivateDataSlotEXT,
+    }
+}
+
+extension_trait! {
+    /// Extension trait adding guarded methods to [`nv::RayTracing`]
+    RayTracingNvExt for nv::RayTracing {
+        create_guarded_acceleration_structure,
+        create_acceleration_structure,
+        vk::AccelerationStructureCreateInfoNV,
+        vk::AccelerationStructureNV,
+    }
+}
+
+extension_trait! {
+    /// Extension trait adding guarded methods to [`khr::Swapchain`]
+    SwapchainExt for khr::Swapchain {
+        create_guarded_swapchain,
+        create_swapchain,
+        vk::SwapchainCreateInfoKHR,
+        vk::SwapchainKHR,
+    }
+}
+
+/// Extension trait adding guarded methods to [`khr::DeferredHostOperations`]
+pub trait DeferredHostOperationsExt: Sized + Deref<Target = khr::DeferredHostOperations> {
+    /// Same as
+    /// [`create_deferred_operation`](khr::DeferredHostOperations::create_deferred_operation) but
+    /// returns guarded [`vk::DeferredOperationKHR`].
+    #[allow(clippy::missing_safety_doc)]
+    unsafe fn create_guarded_deferred_operation<'a>(
+        &self,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::DeferredOperationKHR, Self>>;
+}
+
+impl<LoaderRef> DeferredHostOperationsExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = khr::DeferredHostOperations>,
+{
+    unsafe fn create_guarded_deferred_operation<'a>(
+        &self,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::DeferredOperationKHR, Self>> {
+        let deferred_operation = self.create_deferred_operation(allocation_callbacks)?;
+        Ok(GuardedResource::new(
+            deferred_operation,
+            self.clone(),
+            allocation_callbacks,
+        ))
+    }
+}
+
+/// Extension trait adding guarded methods to [`khr::Display`]
+///
+/// Display modes can't be destroyed, so dropping a guarded [`vk::DisplayModeKHR`] does nothing;
+/// guarding one is only useful for keeping it together with other guarded resources.
+pub trait DisplayExt: Sized + Deref<Target = khr::Display> {
+    /// Same as [`create_display_mode`](khr::Display::create_display_mode) but returns guarded
+    /// [`vk::DisplayModeKHR`].
+    #[allow(clippy::missing_safety_doc)]
+    unsafe fn create_guarded_display_mode<'a>(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        display: vk::DisplayKHR,
+        create_info: &vk::DisplayModeCreateInfoKHR,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::DisplayModeKHR, Self>>;
+}
+
+impl<LoaderRef> DisplayExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = khr::Display>,
+{
+    unsafe fn create_guarded_display_mode<'a>(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        display: vk::DisplayKHR,
+        create_info: &vk::DisplayModeCreateInfoKHR,
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> VkResult<GuardedResource<'a, vk::DisplayModeKHR, Self>> {
+        let display_mode =
+            self.create_display_mode(physical_device, display, create_info, allocation_callbacks)?;
+        Ok(GuardedResource::new(
+            display_mode,
+            self.clone(),
+            allocation_callbacks,
+        ))
+    }
+}
+
+/// Extension trait adding guarded methods to
+/// [`PhysicalDeviceFn<vk::ExtDirectModeDisplayFn>`](PhysicalDeviceFn), which releases displays
+pub trait DirectModeDisplayExt:
+    Sized + Deref<Target = PhysicalDeviceFn<vk::ExtDirectModeDisplayFn>>
+{
+    /// Same as [`get_drm_display`](ext::AcquireDrmDisplay::get_drm_display) followed by
+    /// [`acquire_drm_display`](ext::AcquireDrmDisplay::acquire_drm_display), but returns a
+    /// guarded [`vk::DisplayKHR`] that is released when dropped.
+    #[allow(clippy::missing_safety_doc)]
+    unsafe fn acquire_guarded_drm_display(
+        &self,
+        acquire_drm_display: &ext::AcquireDrmDisplay,
+        drm_fd: i32,
+        connector_id: u32,
+    ) -> VkResult<GuardedResource<'static, vk::DisplayKHR, Self>>;
+}
+
+impl<LoaderRef> DirectModeDisplayExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = PhysicalDeviceFn<vk::ExtDirectModeDisplayFn>>,
+{
+    unsafe fn acquire_guarded_drm_display(
+        &self,
+        acquire_drm_display: &ext::AcquireDrmDisplay,
+        drm_fd: i32,
+        connector_id: u32,
+    ) -> VkResult<GuardedResource<'static, vk::DisplayKHR, Self>> {
+        let physical_device = self.physical_device();
+        let display = acquire_drm_display.get_drm_display(physical_device, drm_fd, connector_id)?;
+        acquire_drm_display.acquire_drm_display(physical_device, drm_fd, display)?;
+        Ok(GuardedResource::new(display, self.clone(), None))
+    }
+}
+
+/// Extension trait adding guarded methods to
+/// [`DeviceFn<vk::IntelPerformanceQueryFn>`](DeviceFn)
+pub trait PerformanceQueryIntelExt:
+    Sized + Deref<Target = DeviceFn<vk::IntelPerformanceQueryFn>>
+{
+    /// Same as `vkAcquirePerformanceConfigurationINTEL` but returns guarded
+    /// [`vk::PerformanceConfigurationINTEL`] that is released when dropped.
+    #[allow(clippy::missing_safety_doc)]
+    unsafe fn acquire_guarded_performance_configuration(
+        &self,
+        acquire_info: &vk::PerformanceConfigurationAcquireInfoINTEL,
+    ) -> VkResult<GuardedResource<'static, vk::PerformanceConfigurationINTEL, Self>>;
+}
+
+impl<LoaderRef> PerformanceQueryIntelExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = DeviceFn<vk::IntelPerformanceQueryFn>>,
+{
+    unsafe fn acquire_guarded_performance_configuration(
+        &self,
+        acquire_info: &vk::PerformanceConfigurationAcquireInfoINTEL,
+    ) -> VkResult<GuardedResource<'static, vk::PerformanceConfigurationINTEL, Self>> {
+        let mut configuration = vk::PerformanceConfigurationINTEL::null();
+        (self.fp().acquire_performance_configuration_intel)(
+            self.device(),
+            acquire_info,
+            &mut configuration,
+        )
+        .result()?;
+        Ok(GuardedResource::new(configuration, self.clone(), None))
+    }
+}
+
+// Extensions without an ash loader are called through a DeviceFn's raw function table.
+
+macro_rules! raw_declaration {
+    ($name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+        #[doc = concat!(
+            "Same as `", stringify!($create), "` but returns guarded [`", stringify!($Resource),
+            "`]."
+        )]
+        unsafe fn $name<'a>(
+            &self,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>>;
+    };
+}
+
+macro_rules! raw_definition {
+    ($name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,) => {
+        unsafe fn $name<'a>(
+            &self,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, $Resource, Self>> {
+            let mut resource = <$Resource>::null();
+            (self.fp().$create)(
+                self.device(),
+                create_info,
+                allocation_callbacks.as_raw_ptr(),
+                &mut resource,
+            )
+            .result()?;
+            Ok(GuardedResource::new(
+                resource,
+                self.clone(),
+                allocation_callbacks,
+            ))
+        }
+    };
+}
+
+macro_rules! raw_extension_trait {
+    (
+        $(#[$attr:meta])*
+        $Trait:ident for $Fp:ty {
+            $($name:ident, $create:ident, $CreateInfo:ty, $Resource:ty,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[allow(clippy::missing_safety_doc)]
+        pub trait $Trait: Sized + Deref<Target = DeviceFn<$Fp>> {
+            $(raw_declaration!($name, $create, $CreateInfo, $Resource,);)*
+        }
+
+        impl<LoaderRef> $Trait for LoaderRef
+        where
+            LoaderRef: Clone + Deref<Target = DeviceFn<$Fp>>,
+        {
+            $(raw_definition!($name, $create, $CreateInfo, $Resource,);)*
+        }
+    };
+}
+
+raw_extension_trait! {
+    /// Extension trait adding guarded methods to [`DeviceFn<vk::NvxBinaryImportFn>`](DeviceFn)
+    BinaryImportNvxExt for vk::NvxBinaryImportFn {
+        create_guarded_cu_module,
+        create_cu_module_nvx,
+        vk::CuModuleCreateInfoNVX,
+        vk::CuModuleNVX,
+
+        create_guarded_cu_function,
+        create_cu_function_nvx,
+        vk::CuFunctionCreateInfoNVX,
+        vk::CuFunctionNVX,
+    }
+}
+
+raw_extension_trait! {
+    /// Extension trait adding guarded methods to
+    /// [`DeviceFn<vk::NvDeviceGeneratedCommandsFn>`](DeviceFn)
+    DeviceGeneratedCommandsNvExt for vk::NvDeviceGeneratedCommandsFn {
+        create_guarded_indirect_commands_layout,
+        create_indirect_commands_layout_nv,
+        vk::IndirectCommandsLayoutCreateInfoNV,
+        vk::IndirectCommandsLayoutNV,
+    }
+}
+
+raw_extension_trait! {
+    /// Extension trait adding guarded methods to [`DeviceFn<vk::ExtValidationCacheFn>`](DeviceFn)
+    ValidationCacheExt for vk::ExtValidationCacheFn {
+        create_guarded_validation_cache,
+        create_validation_cache_ext,
+        vk::ValidationCacheCreateInfoEXT,
+        vk::ValidationCacheEXT,
+    }
+}
+
+raw_extension_trait! {
+    /// Extension trait adding guarded methods to [`DeviceFn<vk::KhrVideoQueueFn>`](DeviceFn)
+    VideoQueueExt for vk::KhrVideoQueueFn {
+        create_guarded_video_session,
+        create_video_session_khr,
+        vk::VideoSessionCreateInfoKHR,
+        vk::VideoSessionKHR,
+
+        create_guarded_video_session_parameters,
+        create_video_session_parameters_khr,
+        vk::VideoSessionParametersCreateInfoKHR,
+        vk::VideoSessionParametersKHR,
+    }
+}
+
+// Surfaces are created by platform-specific loaders but destroyed by khr::Surface, so the
+// creating loader is passed in and the guard holds onto the khr::Surface.
+
+macro_rules! surface_declaration {
+    ($name:ident, $module:ident::$Creator:ident, $create:ident, $CreateInfo:ty,) => {
+        #[doc = concat!(
+            "Same as [`", stringify!($create), "`](ash::extensions::", stringify!($module), "::",
+            stringify!($Creator), "::", stringify!($create), ") but returns guarded ",
+            "[`vk::SurfaceKHR`]."
+        )]
+        unsafe fn $name<'a>(
+            &self,
+            creator: &$module::$Creator,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, vk::SurfaceKHR, Self>>;
+    };
+}
+
+macro_rules! surface_definition {
+    ($name:ident, $module:ident::$Creator:ident, $create:ident, $CreateInfo:ty,) => {
+        unsafe fn $name<'a>(
+            &self,
+            creator: &$module::$Creator,
+            create_info: &$CreateInfo,
+            allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+        ) -> VkResult<GuardedResource<'a, vk::SurfaceKHR, Self>> {
+            let surface = creator.$create(create_info, allocation_callbacks)?;
+            Ok(GuardedResource::new(
+                surface,
+                self.clone(),
+                allocation_callbacks,
+            ))
+        }
+    };
+}
+
+macro_rules! surface_methods {
+    ($method:ident) => {
+        $method!(
+            create_guarded_android_surface,
+            khr::AndroidSurface,
+            create_android_surface,
+            vk::AndroidSurfaceCreateInfoKHR,
+        );
+
+        $method!(
+            create_guarded_display_plane_surface,
+            khr::Display,
+            create_display_plane_surface,
+            vk::DisplaySurfaceCreateInfoKHR,
+        );
+
+        $method!(
+            create_guarded_headless_surface,
+            ext::HeadlessSurface,
+            create_headless_surface,
+            vk::HeadlessSurfaceCreateInfoEXT,
+        );
+
+        $method!(
+            create_guarded_ios_surface,
+            mvk::IOSSurface,
+            create_ios_surface,
+            vk::IOSSurfaceCreateInfoMVK,
+        );
+
+        $method!(
+            create_guarded_mac_os_surface,
+            mvk::MacOSSurface,
+            create_mac_os_surface,
+            vk::MacOSSurfaceCreateInfoMVK,
+        );
+
+        $method!(
+            create_guarded_metal_surface,
+            ext::MetalSurface,
+            create_metal_surface,
+            vk::MetalSurfaceCreateInfoEXT,
+        );
+
+        $method!(
+            create_guarded_vi_surface,
+            nn::ViSurface,
+            create_vi_surface,
+            vk::ViSurfaceCreateInfoNN,
+        );
+
+        $method!(
+            create_guarded_wayland_surface,
+            khr::WaylandSurface,
+            create_wayland_surface,
+            vk::WaylandSurfaceCreateInfoKHR,
+        );
+
+        $method!(
+            create_guarded_win32_surface,
+            khr::Win32Surface,
+            create_win32_surface,
+            vk::Win32SurfaceCreateInfoKHR,
+        );
+
+        $method!(
+            create_guarded_xcb_surface,
+            khr::XcbSurface,
+            create_xcb_surface,
+            vk::XcbSurfaceCreateInfoKHR,
+        );
+
+        $method!(
+            create_guarded_xlib_surface,
+            khr::XlibSurface,
+            create_xlib_surface,
+            vk::XlibSurfaceCreateInfoKHR,
+        );
+    };
+}
+
+/// Extension trait adding guarded methods to [`khr::Surface`]
+///
+/// [`vk::SurfaceKHR`]s are created by platform-specific loaders (such as [`khr::XlibSurface`])
+/// but destroyed by [`khr::Surface`], so each method takes the creating loader as an extra
+/// argument:
+///
+/// ```
+/// # use ash::{extensions::khr, prelude::VkResult, vk};
+/// use ashpan::{Guarded, SurfaceExt};
+///
+/// unsafe fn create_surface<'a>(
+///     surface_fn: &'a khr::Surface,
+///     xlib_surface_fn: &khr::XlibSurface,
+/// ) -> VkResult<Guarded<'a, vk::SurfaceKHR>> {
+///     let create_info = unimplemented!();
+///     surface_fn.create_guarded_xlib_surface(xlib_surface_fn, create_info, None)
+/// }
+/// ```
+#[allow(clippy::missing_safety_doc)]
+pub trait SurfaceExt: Sized + Deref<Target = khr::Surface> {
+    surface_methods!(surface_declaration);
+}
+
+impl<LoaderRef> SurfaceExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = khr::Surface>,
+{
+    surface_methods!(surface_definition);
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::{
+        extensions::{ext, khr, nv},
+        vk,
+    };
+
+    use crate::{
+        mock, AccelerationStructureExt, BinaryImportNvxExt, DebugUtilsExt,
+        DeferredHostOperationsExt, DeviceFn, DeviceGeneratedCommandsNvExt, DirectModeDisplayExt,
+        DisplayExt, PerformanceQueryIntelExt, PhysicalDeviceFn, PrivateDataExt, RayTracingNvExt,
+        SurfaceExt, SwapchainExt, ValidationCacheExt, VideoQueueExt,
+    };
+
+    fn created_and_destroyed(
+        create: &'static str,
+        destroy: &'static str,
+        handle: impl vk::Handle + Copy,
+        allocation_callbacks: &vk::AllocationCallbacks,
+    ) -> [mock::Call; 2] {
+        [
+            mock::Call::new(create, handle, Some(allocation_callbacks)),
+            mock::Call::new(destroy, handle, Some(allocation_callbacks)),
+        ]
+    }
+
+    #[test]
+    fn create_guarded_acceleration_structure_khr() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::AccelerationStructure::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let acceleration_structure = unsafe {
+            (&loader).create_guarded_acceleration_structure(
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *acceleration_structure;
+        drop(acceleration_structure);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateAccelerationStructureKHR",
+                "vkDestroyAccelerationStructureKHR",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_acceleration_structure_nv() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = nv::RayTracing::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let acceleration_structure = unsafe {
+            (&loader).create_guarded_acceleration_structure(
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *acceleration_structure;
+        drop(acceleration_structure);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateAccelerationStructureNV",
+                "vkDestroyAccelerationStructureNV",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_debug_utils_messenger() {
+        let entry = mock::entry();
+        let (instance, _device) = mock::instance_and_device();
+        let loader = ext::DebugUtils::new(&entry, &instance);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let messenger = unsafe {
+            (&loader).create_guarded_debug_utils_messenger(
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *messenger;
+        drop(messenger);
+
+        assert_eq!(
+            mock::calls(&instance)[1..],
+            created_and_destroyed(
+                "vkCreateDebugUtilsMessengerEXT",
+                "vkDestroyDebugUtilsMessengerEXT",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_deferred_operation() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::DeferredHostOperations::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let deferred_operation =
+            unsafe { (&loader).create_guarded_deferred_operation(Some(&allocation_callbacks)) }
+                .unwrap();
+        let handle = *deferred_operation;
+        drop(deferred_operation);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateDeferredOperationKHR",
+                "vkDestroyDeferredOperationKHR",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_headless_surface() {
+        let entry = mock::entry();
+        let (instance, _device) = mock::instance_and_device();
+        let surface_fn = khr::Surface::new(&entry, &instance);
+        let headless_surface_fn = ext::HeadlessSurface::new(&entry, &instance);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let surface = unsafe {
+            (&surface_fn).create_guarded_headless_surface(
+                &headless_surface_fn,
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *surface;
+        drop(surface);
+
+        assert_eq!(
+            mock::calls(&instance)[1..],
+            created_and_destroyed(
+                "vkCreateHeadlessSurfaceEXT",
+                "vkDestroySurfaceKHR",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_swapchain() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::Swapchain::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let swapchain = unsafe {
+            (&loader).create_guarded_swapchain(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let handle = *swapchain;
+        drop(swapchain);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateSwapchainKHR",
+                "vkDestroySwapchainKHR",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_private_data_slot() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = ext::PrivateData::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let private_data_slot = unsafe {
+            (&loader)
+                .create_guarded_private_data_slot(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let handle = *private_data_slot;
+        drop(private_data_slot);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreatePrivateDataSlotEXT",
+                "vkDestroyPrivateDataSlotEXT",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_display_mode() {
+        let entry = mock::entry();
+        let (instance, _device) = mock::instance_and_device();
+        let loader = khr::Display::new(&entry, &instance);
+        let physical_device = mock::physical_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let display_mode = unsafe {
+            (&loader).create_guarded_display_mode(
+                physical_device,
+                vk::DisplayKHR::null(),
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *display_mode;
+        drop(display_mode);
+
+        // Display modes can't be destroyed
+        assert_eq!(
+            mock::calls(&physical_device),
+            [mock::Call::new(
+                "vkCreateDisplayModeKHR",
+                handle,
+                Some(&allocation_callbacks)
+            )]
+        );
+    }
+
+    #[test]
+    fn acquire_guarded_drm_display() {
+        let entry = mock::entry();
+        let (instance, _device) = mock::instance_and_device();
+        let physical_device = mock::physical_device();
+        let acquire_drm_display = ext::AcquireDrmDisplay::new(&entry, &instance);
+        let loader = PhysicalDeviceFn::new(&entry, &instance, physical_device);
+
+        let display =
+            unsafe { (&loader).acquire_guarded_drm_display(&acquire_drm_display, 3, 7) }.unwrap();
+        let handle = *display;
+        drop(display);
+
+        assert_eq!(
+            mock::calls(&physical_device),
+            [
+                mock::Call::new("vkAcquireDrmDisplayEXT", handle, None),
+                mock::Call::new("vkReleaseDisplayEXT", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn acquire_guarded_performance_configuration() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = DeviceFn::<vk::IntelPerformanceQueryFn>::new(&instance, &device);
+
+        let configuration =
+            unsafe { (&loader).acquire_guarded_performance_configuration(&Default::default()) }
+                .unwrap();
+        let handle = *configuration;
+        drop(configuration);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkAcquirePerformanceConfigurationINTEL", handle, None),
+                mock::Call::new("vkReleasePerformanceConfigurationINTEL", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_guarded_cu_module_and_function() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = DeviceFn::<vk::NvxBinaryImportFn>::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let module = unsafe {
+            (&loader).create_guarded_cu_module(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let function = unsafe {
+            (&loader).create_guarded_cu_function(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let (module_handle, function_handle) = (*module, *function);
+        drop(function);
+        drop(module);
+
+        let callbacks = Some(&allocation_callbacks);
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateCuModuleNVX", module_handle, callbacks),
+                mock::Call::new("vkCreateCuFunctionNVX", function_handle, callbacks),
+                mock::Call::new("vkDestroyCuFunctionNVX", function_handle, callbacks),
+                mock::Call::new("vkDestroyCuModuleNVX", module_handle, callbacks),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_guarded_indirect_commands_layout() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = DeviceFn::<vk::NvDeviceGeneratedCommandsFn>::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let layout = unsafe {
+            (&loader).create_guarded_indirect_commands_layout(
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let handle = *layout;
+        drop(layout);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateIndirectCommandsLayoutNV",
+                "vkDestroyIndirectCommandsLayoutNV",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_validation_cache() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = DeviceFn::<vk::ExtValidationCacheFn>::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let validation_cache = unsafe {
+            (&loader)
+                .create_guarded_validation_cache(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let handle = *validation_cache;
+        drop(validation_cache);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            created_and_destroyed(
+                "vkCreateValidationCacheEXT",
+                "vkDestroyValidationCacheEXT",
+                handle,
+                &allocation_callbacks,
+            )
+        );
+    }
+
+    #[test]
+    fn create_guarded_video_session_and_parameters() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = DeviceFn::<vk::KhrVideoQueueFn>::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+
+        let session = unsafe {
+            (&loader).create_guarded_video_session(&Default::default(), Some(&allocation_callbacks))
+        }
+        .unwrap();
+        let parameters = unsafe {
+            (&loader).create_guarded_video_session_parameters(
+                &Default::default(),
+                Some(&allocation_callbacks),
+            )
+        }
+        .unwrap();
+        let (session_handle, parameters_handle) = (*session, *parameters);
+        drop(parameters);
+        drop(session);
+
+        let callbacks = Some(&allocation_callbacks);
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateVideoSessionKHR", session_handle, callbacks),
+                mock::Call::new(
+                    "vkCreateVideoSessionParametersKHR",
+                    parameters_handle,
+                    callbacks
+                ),
+                mock::Call::new(
+                    "vkDestroyVideoSessionParametersKHR",
+                    parameters_handle,
+                    callbacks
+                ),
+                mock::Call::new("vkDestroyVideoSessionKHR", session_handle, callbacks),
+            ]
+        );
+    }
+}
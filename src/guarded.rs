@@ -1,10 +1,14 @@
 use std::borrow::{Borrow, BorrowMut};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::{AsMut, AsRef};
+use std::hash::{BuildHasher, Hash};
 use std::ops::{Deref, DerefMut};
 
 use ash::vk;
 
-use crate::Destroyable;
+use crate::{
+    DeletionQueue, Destroyable, DynDestroyable, DynGuarded, Reversed, WaitForFences, WaitIdle,
+};
 
 /// Most common usecase for [`GuardedResource`]
 ///
@@ -46,6 +50,10 @@ pub type Guarded<'d, Resource> =
 ///     Ok(Guarded::new(pipeline, device, None))
 /// }
 /// ```
+///
+/// If destroying the resource panics, the panic propagates out of `drop` (after the remaining
+/// elements of containers have been destroyed). As with any destructor, panicking while the
+/// thread is already unwinding aborts the process.
 #[derive(Debug)]
 pub struct GuardedResource<'alloc_cb, Resource, Destroyer>(
     // Invariant: The option is always Some, except possibly while being dropped.
@@ -79,6 +87,8 @@ where
         destroyer: Destroyer,
         allocation_callbacks: Option<&'alloc_cb vk::AllocationCallbacks>,
     ) -> Self {
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&resource);
         Self(Some(ResourceAndDestroyer {
             resource,
             destroyer,
@@ -94,6 +104,10 @@ where
         self.0.as_ref().unwrap().destroyer.clone()
     }
 
+    pub(crate) fn destroyer_ref(&self) -> &Destroyer {
+        &self.0.as_ref().unwrap().destroyer
+    }
+
     /// Returns the allocation callbacks passed during construction.
     pub fn allocation_callbacks(&self) -> Option<&'alloc_cb vk::AllocationCallbacks> {
         self.0.as_ref().unwrap().allocation_callbacks
@@ -108,7 +122,230 @@ where
     /// this is a method because it's not intended to work with arbitrary types, so avoiding
     /// shadowing `.take()` is less important than convenience.
     pub fn take(mut self) -> Resource {
-        self.0.take().unwrap().resource
+        let resource = self.0.take().unwrap().resource;
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::hand_off(&resource);
+        resource
+    }
+
+    /// Guards `resource` in place of the current resource, which is returned in a new guard with
+    /// the same destroyer and `allocation_callbacks`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new).
+    pub unsafe fn replace(&mut self, resource: Resource) -> Self
+    where
+        Destroyer: Clone,
+    {
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&resource);
+        let parts = self.0.as_mut().unwrap();
+        Self(Some(ResourceAndDestroyer {
+            resource: std::mem::replace(&mut parts.resource, resource),
+            destroyer: parts.destroyer.clone(),
+            allocation_callbacks: parts.allocation_callbacks,
+        }))
+    }
+
+    /// Guards `resource` in place of the current resource, which is destroyed immediately.
+    ///
+    /// ```
+    /// # use ash::{prelude::VkResult, vk};
+    /// use ashpan::Guarded;
+    ///
+    /// unsafe fn reload_shader(
+    ///     device: &ash::Device,
+    ///     shader_module: &mut Guarded<vk::ShaderModule>,
+    ///     code: &[u32],
+    /// ) -> VkResult<()> {
+    ///     let create_info = vk::ShaderModuleCreateInfo::builder().code(code);
+    ///     // If compilation fails, the old shader module is kept
+    ///     shader_module.reset(device.create_shader_module(&create_info, None)?);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new). Additionally, it must be safe to destroy the current resource.
+    pub unsafe fn reset(&mut self, resource: Resource) {
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&resource);
+        let parts = self.0.as_mut().unwrap();
+        let mut old_resource = std::mem::replace(&mut parts.resource, resource);
+        old_resource.destroy_with(&parts.destroyer, parts.allocation_callbacks);
+    }
+
+    /// Converts the resource with `f`, guarding the result with the same destroyer and
+    /// `allocation_callbacks`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new), for the resource returned by `f`.
+    pub unsafe fn map<NewResource>(
+        self,
+        f: impl FnOnce(Resource) -> NewResource,
+    ) -> GuardedResource<'alloc_cb, NewResource, Destroyer>
+    where
+        NewResource: Destroyable<Destroyer = <Resource as Destroyable>::Destroyer>,
+    {
+        let ResourceAndDestroyer {
+            resource,
+            destroyer,
+            allocation_callbacks,
+        } = self.into_parts();
+        GuardedResource::new(f(resource), destroyer, allocation_callbacks)
+    }
+
+    /// Like [`map`](Self::map), but `f` may fail by handing the resource back, in which case the
+    /// original guard is returned.
+    ///
+    /// ```
+    /// # use ash::vk;
+    /// use ashpan::Guarded;
+    ///
+    /// fn into_array<'d>(
+    ///     image_views: Guarded<'d, Vec<vk::ImageView>>,
+    /// ) -> Result<Guarded<'d, [vk::ImageView; 3]>, Guarded<'d, Vec<vk::ImageView>>> {
+    ///     // The same image views are guarded with the same device
+    ///     unsafe { image_views.try_map(<[_; 3]>::try_from) }
+    /// }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new), for the resource returned by `f`.
+    pub unsafe fn try_map<NewResource>(
+        self,
+        f: impl FnOnce(Resource) -> Result<NewResource, Resource>,
+    ) -> Result<GuardedResource<'alloc_cb, NewResource, Destroyer>, Self>
+    where
+        NewResource: Destroyable<Destroyer = <Resource as Destroyable>::Destroyer>,
+    {
+        let ResourceAndDestroyer {
+            resource,
+            destroyer,
+            allocation_callbacks,
+        } = self.into_parts();
+        match f(resource) {
+            Ok(new_resource) => Ok(GuardedResource::new(
+                new_resource,
+                destroyer,
+                allocation_callbacks,
+            )),
+            Err(resource) => Err(Self::new(resource, destroyer, allocation_callbacks)),
+        }
+    }
+
+    /// Disarms the guard, returning everything needed to guard the resource again.
+    fn into_parts(mut self) -> ResourceAndDestroyer<'alloc_cb, Resource, Destroyer> {
+        let parts = self.0.take().unwrap();
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::hand_off(&parts.resource);
+        parts
+    }
+
+    /// Erases the resource and destroyer types, so that the guard can be stored alongside
+    /// unrelated ones. See [`DynGuarded`].
+    pub fn into_dyn<'a>(mut self) -> DynGuarded<'a>
+    where
+        'alloc_cb: 'a,
+        Resource: 'a,
+        Destroyer: 'a,
+    {
+        // Not handed off, so that leak tracking still reports the resource under its own type.
+        let parts = self.0.take().unwrap();
+        GuardedResource(Some(ResourceAndDestroyer {
+            resource: Box::new(parts),
+            destroyer: &(),
+            allocation_callbacks: None,
+        }))
+    }
+
+    /// Hands the resource to `deletion_queue` to be destroyed once `fence` has signaled. Same as
+    /// [`DeletionQueue::defer_until`].
+    pub fn defer_until<'q, DeviceRef>(
+        self,
+        deletion_queue: &mut DeletionQueue<'q, DeviceRef>,
+        fence: vk::Fence,
+    ) where
+        Self: 'q,
+        DeviceRef: Deref<Target = ash::Device>,
+    {
+        deletion_queue.defer_until(fence, self);
+    }
+
+    /// Hands the resource to `deletion_queue` to be destroyed `frames` frames from now. Same as
+    /// [`DeletionQueue::defer_frames`].
+    pub fn defer_frames<'q, DeviceRef>(
+        self,
+        deletion_queue: &mut DeletionQueue<'q, DeviceRef>,
+        frames: u64,
+    ) where
+        Self: 'q,
+        DeviceRef: Deref<Target = ash::Device>,
+    {
+        deletion_queue.defer_frames(frames, self);
+    }
+}
+
+impl<'alloc_cb, Resource, Destroyer> DynDestroyable
+    for ResourceAndDestroyer<'alloc_cb, Resource, Destroyer>
+where
+    Resource: Destroyable,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    unsafe fn destroy(&mut self) {
+        self.resource
+            .destroy_with(&self.destroyer, self.allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        self.resource.visit_handles(visitor);
+    }
+}
+
+impl<'alloc_cb, Destroyer> GuardedResource<'alloc_cb, ash::Device, Destroyer>
+where
+    Destroyer: Deref<Target = ()>,
+{
+    /// Makes the device wait until it's idle before being destroyed. See [`WaitIdle`].
+    pub fn wait_idle_on_drop(self) -> GuardedResource<'alloc_cb, WaitIdle, Destroyer> {
+        let ResourceAndDestroyer {
+            resource,
+            destroyer,
+            allocation_callbacks,
+        } = self.into_parts();
+        // The same device is guarded with the same destroyer.
+        unsafe { GuardedResource::new(WaitIdle::new(resource), destroyer, allocation_callbacks) }
+    }
+}
+
+impl<'alloc_cb, Fences, Destroyer> GuardedResource<'alloc_cb, Fences, Destroyer>
+where
+    Fences: AsRef<[vk::Fence]> + Destroyable<Destroyer = ash::Device>,
+    Destroyer: Deref<Target = ash::Device>,
+{
+    /// Makes the fences wait up to `timeout` nanoseconds to be signaled before being destroyed.
+    /// See [`WaitForFences`].
+    pub fn wait_for_fences_on_drop(
+        self,
+        timeout: u64,
+    ) -> GuardedResource<'alloc_cb, WaitForFences<Fences>, Destroyer> {
+        let ResourceAndDestroyer {
+            resource,
+            destroyer,
+            allocation_callbacks,
+        } = self.into_parts();
+        // The same fences are guarded with the same destroyer.
+        unsafe {
+            GuardedResource::new(
+                WaitForFences::new(resource, timeout),
+                destroyer,
+                allocation_callbacks,
+            )
+        }
     }
 }
 
@@ -121,7 +358,8 @@ where
     /// `destroyer` and `allocation_callbacks` are used during destruction.
     ///
     /// If the iterator returns an error, then iteration is aborted and all resources created thus
-    /// far are destroyed in first-to-last order.
+    /// far are destroyed in first-to-last order. Use
+    /// [`try_new_reversed_from`](GuardedResource::try_new_reversed_from) for last-to-first order.
     ///
     /// # Safety
     ///
@@ -174,6 +412,8 @@ where
         for resource in resources {
             guarded_resources.push(resource?);
         }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&*guarded_resources);
         Ok(guarded_resources)
     }
 }
@@ -189,7 +429,8 @@ where
     ///
     /// The array of resources is populated by repeatedly calling `resource_factory(index)`.
     /// If an error is encountered, resource creation is aborted and all resources created thus
-    /// far are destroyed in first-to-last order.
+    /// far are destroyed in first-to-last order. Use
+    /// [`try_new_reversed_with`](GuardedResource::try_new_reversed_with) for last-to-first order.
     ///
     /// # Safety
     ///
@@ -250,6 +491,173 @@ where
     }
 }
 
+impl<'alloc_cb, Resource, Destroyer> GuardedResource<'alloc_cb, Reversed<Vec<Resource>>, Destroyer>
+where
+    Resource: Destroyable,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    /// Same as [`try_new_from`](GuardedResource::try_new_from), but resources are destroyed in
+    /// last-to-first order, both upon error and when the [`GuardedResource`] is dropped.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that it is safe to destroy the resources when the [`GuardedResource`] is
+    /// dropped.
+    pub unsafe fn try_new_reversed_from<E>(
+        resources: impl IntoIterator<Item = Result<Resource, E>>,
+        destroyer: Destroyer,
+        allocation_callbacks: Option<&'alloc_cb vk::AllocationCallbacks>,
+    ) -> Result<Self, E> {
+        let resources = resources.into_iter();
+        let (min_capacity, _) = resources.size_hint();
+        let mut guarded_resources = Self::new(
+            Reversed(Vec::with_capacity(min_capacity)),
+            destroyer,
+            allocation_callbacks,
+        );
+        for resource in resources {
+            guarded_resources.push(resource?);
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&*guarded_resources);
+        Ok(guarded_resources)
+    }
+}
+
+impl<'alloc_cb, Resource, Destroyer, const N: usize>
+    GuardedResource<'alloc_cb, Reversed<[Resource; N]>, Destroyer>
+where
+    Resource: Destroyable,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    /// Same as [`try_new_with`](GuardedResource::try_new_with), but resources are destroyed in
+    /// last-to-first order, both upon error and when the [`GuardedResource`] is dropped.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that it is safe to destroy the resources when the [`GuardedResource`] is
+    /// dropped.
+    pub unsafe fn try_new_reversed_with<E>(
+        resource_factory: impl FnMut(usize) -> Result<Resource, E>,
+        destroyer: Destroyer,
+        allocation_callbacks: Option<&'alloc_cb vk::AllocationCallbacks>,
+    ) -> Result<Self, E> {
+        let resources = GuardedResource::try_new_reversed_from(
+            (0..N).map(resource_factory),
+            &*destroyer,
+            allocation_callbacks,
+        )?;
+        let resources = resources
+            .take()
+            .into_inner()
+            .try_into()
+            .unwrap_or_else(|_| {
+                unreachable!("Bug in GuardedResource::try_new_reversed_with(): wrong length")
+            });
+        Ok(Self::new(
+            Reversed(resources),
+            destroyer,
+            allocation_callbacks,
+        ))
+    }
+}
+
+impl<'alloc_cb, Key, Resource, S, Destroyer>
+    GuardedResource<'alloc_cb, HashMap<Key, Resource, S>, Destroyer>
+where
+    Key: Eq + Hash,
+    Resource: Destroyable,
+    S: BuildHasher + Default,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    /// Creates a [`GuardedResource`] to hold a [`HashMap`] populated from `entries`.
+    /// `destroyer` and `allocation_callbacks` are used during destruction.
+    ///
+    /// If the iterator returns an error, then iteration is aborted and all resources created thus
+    /// far are destroyed. If a key occurs more than once, the resource it previously mapped to is
+    /// destroyed immediately.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that it is safe to destroy the resources when the [`GuardedResource`] is
+    /// dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use ash::{prelude::VkResult, vk};
+    /// use ashpan::Guarded;
+    ///
+    /// unsafe fn create_samplers<'a>(
+    ///     device: &'a ash::Device,
+    ///     filters: &[vk::Filter],
+    /// ) -> VkResult<Guarded<'a, HashMap<vk::Filter, vk::Sampler>>> {
+    ///     let samplers = filters.iter().map(|&filter| {
+    ///         let create_info = vk::SamplerCreateInfo::builder()
+    ///             .mag_filter(filter)
+    ///             .min_filter(filter);
+    ///         Ok((filter, device.create_sampler(&create_info, None)?))
+    ///     });
+    ///     Guarded::try_new_hash_map_from(samplers, device, None)
+    /// }
+    /// ```
+    pub unsafe fn try_new_hash_map_from<E>(
+        entries: impl IntoIterator<Item = Result<(Key, Resource), E>>,
+        destroyer: Destroyer,
+        allocation_callbacks: Option<&'alloc_cb vk::AllocationCallbacks>,
+    ) -> Result<Self, E> {
+        let entries = entries.into_iter();
+        let (min_capacity, _) = entries.size_hint();
+        let mut guarded_resources = Self::new(
+            HashMap::with_capacity_and_hasher(min_capacity, S::default()),
+            destroyer,
+            allocation_callbacks,
+        );
+        for entry in entries {
+            let (key, resource) = entry?;
+            if let Some(mut replaced) = guarded_resources.insert(key, resource) {
+                replaced.destroy_with(guarded_resources.destroyer_ref(), allocation_callbacks);
+            }
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&*guarded_resources);
+        Ok(guarded_resources)
+    }
+}
+
+impl<'alloc_cb, Key, Resource, Destroyer>
+    GuardedResource<'alloc_cb, BTreeMap<Key, Resource>, Destroyer>
+where
+    Key: Ord,
+    Resource: Destroyable,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    /// Same as [`try_new_hash_map_from`](GuardedResource::try_new_hash_map_from), but for a
+    /// [`BTreeMap`].
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that it is safe to destroy the resources when the [`GuardedResource`] is
+    /// dropped.
+    pub unsafe fn try_new_btree_map_from<E>(
+        entries: impl IntoIterator<Item = Result<(Key, Resource), E>>,
+        destroyer: Destroyer,
+        allocation_callbacks: Option<&'alloc_cb vk::AllocationCallbacks>,
+    ) -> Result<Self, E> {
+        let mut guarded_resources = Self::new(BTreeMap::new(), destroyer, allocation_callbacks);
+        for entry in entries {
+            let (key, resource) = entry?;
+            if let Some(mut replaced) = guarded_resources.insert(key, resource) {
+                replaced.destroy_with(guarded_resources.destroyer_ref(), allocation_callbacks);
+            }
+        }
+        #[cfg(feature = "leak-tracking")]
+        crate::leak::register(&*guarded_resources);
+        Ok(guarded_resources)
+    }
+}
+
 impl<'alloc_cb, Resource, Destroyer> AsRef<Resource>
     for GuardedResource<'alloc_cb, Resource, Destroyer>
 where
@@ -257,7 +665,7 @@ where
     Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
 {
     fn as_ref(&self) -> &Resource {
-        &*self
+        self
     }
 }
 
@@ -279,7 +687,7 @@ where
     Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
 {
     fn borrow(&self) -> &Resource {
-        &*self
+        self
     }
 }
 
@@ -335,7 +743,12 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{Destroyable, Guarded, GuardedResource};
+    use std::cell::RefCell;
+    use std::collections::{BTreeMap, HashMap, VecDeque};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::process::Command;
+
+    use crate::{Destroyable, Guarded, GuardedResource, Reversed};
     use ash::vk;
 
     #[derive(Debug, PartialEq)]
@@ -386,6 +799,37 @@ mod tests {
         }
     }
 
+    #[derive(Debug)]
+    struct Logged<'a>(&'a RefCell<Vec<u32>>, u32);
+
+    impl<'a> Destroyable for Logged<'a> {
+        type Destroyer = ();
+
+        unsafe fn destroy_with(
+            &mut self,
+            _destroyer: &(),
+            _allocation_callbacks: Option<&vk::AllocationCallbacks>,
+        ) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    /// Like [`Logged`], but panics after logging if the flag is set.
+    struct Panicking<'a>(Logged<'a>, bool);
+
+    impl<'a> Destroyable for Panicking<'a> {
+        type Destroyer = ();
+
+        unsafe fn destroy_with(
+            &mut self,
+            destroyer: &(),
+            allocation_callbacks: Option<&vk::AllocationCallbacks>,
+        ) {
+            self.0.destroy_with(destroyer, allocation_callbacks);
+            assert!(!self.1, "failed to destroy {}", self.0 .1);
+        }
+    }
+
     #[test]
     fn methods_can_be_called_on_guarded_resource() {
         let mut guarded = unsafe { Guarded::new(TestWrapper(12332), &(), None) };
@@ -650,4 +1094,290 @@ mod tests {
 
         assert_eq!(guarded.unwrap_err(), "oh no");
     }
+
+    #[test]
+    fn reversed_vec_destroys_elements_last_to_first() {
+        let log = RefCell::new(Vec::new());
+        let resources_to_create = (0..3).map(|i| Result::<_, ()>::Ok(Logged(&log, i)));
+
+        let guarded =
+            unsafe { GuardedResource::try_new_reversed_from(resources_to_create, &(), None) }
+                .unwrap();
+        assert_eq!(guarded.len(), 3);
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn reversed_vec_drops_previously_created_elements_last_to_first_upon_error() {
+        let log = RefCell::new(Vec::new());
+        let resources_to_create = [
+            Ok(Logged(&log, 0)),
+            Ok(Logged(&log, 1)),
+            Ok(Logged(&log, 2)),
+            Err("oh no"),
+            Ok(Logged(&log, 3)),
+        ];
+
+        let guarded: Result<Guarded<Reversed<Vec<_>>>, _> =
+            unsafe { GuardedResource::try_new_reversed_from(resources_to_create, &(), None) };
+
+        assert_eq!(guarded.err(), Some("oh no"));
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn reversed_array_destroys_elements_last_to_first() {
+        let log = RefCell::new(Vec::new());
+
+        let guarded: Guarded<Reversed<[_; 3]>> = unsafe {
+            GuardedResource::try_new_reversed_with(
+                |i| Result::<_, ()>::Ok(Logged(&log, i as u32)),
+                &(),
+                None,
+            )
+        }
+        .unwrap();
+        assert_eq!(guarded.len(), 3);
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn reversed_array_drops_previously_created_elements_last_to_first_upon_error() {
+        let log = RefCell::new(Vec::new());
+
+        let guarded: Result<Guarded<Reversed<[_; 4]>>, _> = unsafe {
+            GuardedResource::try_new_reversed_with(
+                |i| match i {
+                    3 => Err("oh no"),
+                    _ => Ok(Logged(&log, i as u32)),
+                },
+                &(),
+                None,
+            )
+        };
+
+        assert_eq!(guarded.err(), Some("oh no"));
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn unreversed_containers_destroy_elements_first_to_last() {
+        let log = RefCell::new(Vec::new());
+
+        drop(unsafe { Guarded::new(vec![Logged(&log, 0), Logged(&log, 1)], &(), None) });
+        drop(unsafe { Guarded::new([Logged(&log, 2), Logged(&log, 3)], &(), None) });
+
+        assert_eq!(*log.borrow(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn containers_destroy_remaining_elements_when_one_panics() {
+        let log = RefCell::new(Vec::new());
+        let panicking = |i, panics| Panicking(Logged(&log, i), panics);
+
+        let vec = vec![panicking(0, false), panicking(1, true), panicking(2, false)];
+        let guarded = unsafe { Guarded::new(vec, &(), None) };
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| drop(guarded))).unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "failed to destroy 1"
+        );
+
+        let array = [panicking(3, true), panicking(4, false)];
+        let guarded = unsafe { Guarded::new(array, &(), None) };
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(guarded))).is_err());
+
+        assert_eq!(*log.borrow(), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn reversed_containers_destroy_remaining_elements_when_one_panics() {
+        let log = RefCell::new(Vec::new());
+        let panicking = |i, panics| Panicking(Logged(&log, i), panics);
+
+        let vec = Reversed(vec![
+            panicking(0, false),
+            panicking(1, true),
+            panicking(2, false),
+        ]);
+        let guarded = unsafe { Guarded::new(vec, &(), None) };
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(guarded))).is_err());
+
+        let array = Reversed([panicking(3, false), panicking(4, true)]);
+        let guarded = unsafe { Guarded::new(array, &(), None) };
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(guarded))).is_err());
+
+        assert_eq!(*log.borrow(), [2, 1, 0, 4, 3]);
+    }
+
+    #[test]
+    fn panicking_again_while_destroying_remaining_elements_aborts() {
+        const CHILD_VAR: &str = "ASHPAN_TEST_DOUBLE_PANIC";
+        const TEST_NAME: &str =
+            "guarded::tests::panicking_again_while_destroying_remaining_elements_aborts";
+
+        if std::env::var_os(CHILD_VAR).is_some() {
+            let log = RefCell::new(Vec::new());
+            let vec = vec![
+                Panicking(Logged(&log, 0), true),
+                Panicking(Logged(&log, 1), true),
+            ];
+            drop(unsafe { Guarded::new(vec, &(), None) });
+            unreachable!();
+        }
+
+        // Aborting would take the whole test binary down, so run this test again in a child
+        // process.
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args([TEST_NAME, "--exact", "--nocapture", "--test-threads=1"])
+            .env(CHILD_VAR, "1")
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        // An ordinary test failure exits with status 101 instead
+        assert_eq!(output.status.code(), None, "{stderr}");
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&output.status),
+            Some(6), // SIGABRT
+            "{stderr}"
+        );
+        assert!(stderr.contains("failed to destroy 0"), "{stderr}");
+        assert!(stderr.contains("failed to destroy 1"), "{stderr}");
+    }
+
+    #[test]
+    fn tuples_destroy_elements_last_to_first() {
+        let log = RefCell::new(Vec::new());
+
+        let tuple = (Logged(&log, 0), Logged(&log, 1), Logged(&log, 2));
+        drop(unsafe { Guarded::new(tuple, &(), None) });
+
+        assert_eq!(*log.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn boxes_and_deques_destroy_their_contents() {
+        let log = RefCell::new(Vec::new());
+
+        drop(unsafe { Guarded::new(Box::new(Logged(&log, 0)), &(), None) });
+        let deque = VecDeque::from([Logged(&log, 1), Logged(&log, 2)]);
+        drop(unsafe { Guarded::new(deque, &(), None) });
+
+        assert_eq!(*log.borrow(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn guarded_maps_destroy_values_upon_drop() {
+        let log = RefCell::new(Vec::new());
+        let entries = (0..3).map(|i| Result::<_, ()>::Ok((i, Logged(&log, i))));
+
+        let guarded: Guarded<HashMap<_, _>> =
+            unsafe { GuardedResource::try_new_hash_map_from(entries.clone(), &(), None) }.unwrap();
+        assert_eq!(guarded[&1].1, 1);
+        drop(guarded);
+        log.borrow_mut().sort_unstable();
+        assert_eq!(*log.borrow(), [0, 1, 2]);
+
+        log.borrow_mut().clear();
+        let guarded: Guarded<BTreeMap<_, _>> =
+            unsafe { GuardedResource::try_new_btree_map_from(entries, &(), None) }.unwrap();
+        drop(guarded);
+        assert_eq!(*log.borrow(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn guarded_maps_destroy_previously_created_values_upon_error() {
+        let log = RefCell::new(Vec::new());
+        let entries = || {
+            [
+                Ok(("a", Logged(&log, 0))),
+                Err("oh no"),
+                Ok(("b", Logged(&log, 1))),
+            ]
+        };
+
+        let guarded: Result<Guarded<HashMap<_, _>>, _> =
+            unsafe { GuardedResource::try_new_hash_map_from(entries(), &(), None) };
+        assert_eq!(guarded.err(), Some("oh no"));
+        let guarded: Result<Guarded<BTreeMap<_, _>>, _> =
+            unsafe { GuardedResource::try_new_btree_map_from(entries(), &(), None) };
+        assert_eq!(guarded.err(), Some("oh no"));
+
+        assert_eq!(*log.borrow(), [0, 0]);
+    }
+
+    #[test]
+    fn guarded_maps_destroy_values_replaced_by_duplicate_keys() {
+        let log = RefCell::new(Vec::new());
+        let entries = [
+            Ok(("a", Logged(&log, 0))),
+            Result::<_, ()>::Ok(("a", Logged(&log, 1))),
+        ];
+
+        let guarded: Guarded<BTreeMap<_, _>> =
+            unsafe { GuardedResource::try_new_btree_map_from(entries, &(), None) }.unwrap();
+        assert_eq!(*log.borrow(), [0]);
+        assert_eq!(guarded["a"].1, 1);
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [0, 1]);
+    }
+
+    #[test]
+    fn replaced_resources_are_returned_in_a_new_guard() {
+        let log = RefCell::new(Vec::new());
+        let mut guarded = unsafe { Guarded::new(Logged(&log, 0), &(), None) };
+
+        let old_guarded = unsafe { guarded.replace(Logged(&log, 1)) };
+        assert_eq!(old_guarded.1, 0);
+        assert_eq!(guarded.1, 1);
+        assert!(log.borrow().is_empty());
+        drop(old_guarded);
+        assert_eq!(*log.borrow(), [0]);
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [0, 1]);
+    }
+
+    #[test]
+    fn reset_resources_are_destroyed_immediately() {
+        let log = RefCell::new(Vec::new());
+        let mut guarded = unsafe { Guarded::new(Logged(&log, 0), &(), None) };
+
+        unsafe { guarded.reset(Logged(&log, 1)) };
+        assert_eq!(*log.borrow(), [0]);
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [0, 1]);
+    }
+
+    #[test]
+    fn mapped_resources_keep_destroyer() {
+        let log = RefCell::new(Vec::new());
+        let guarded = unsafe { Guarded::new(vec![Logged(&log, 0), Logged(&log, 1)], &(), None) };
+
+        drop(unsafe { guarded.map(Reversed) });
+
+        assert_eq!(*log.borrow(), [1, 0]);
+    }
+
+    #[test]
+    fn failed_try_map_returns_original_guard() {
+        let log = RefCell::new(Vec::new());
+        let guarded = unsafe { Guarded::new(vec![Logged(&log, 0), Logged(&log, 1)], &(), None) };
+
+        let guarded = unsafe { guarded.try_map(<[_; 3]>::try_from) }.unwrap_err();
+        assert!(log.borrow().is_empty());
+        let guarded = unsafe { guarded.try_map(<[_; 2]>::try_from) }.unwrap();
+        drop(guarded);
+
+        assert_eq!(*log.borrow(), [0, 1]);
+    }
 }
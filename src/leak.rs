@@ -0,0 +1,276 @@
+//! Registry of live guarded resources, for finding leaks
+//!
+//! Available with the `leak-tracking` feature. Whenever a [`GuardedResource`] is created, each
+//! Vulkan handle reported by [`Destroyable::visit_handles`] is registered along with the
+//! resource's type and a backtrace of where the guard was created. Handles are unregistered when
+//! they're destroyed via [`Destroyable::destroy_with`], so anything still registered at shutdown
+//! was leaked:
+//!
+//! ```
+//! unsafe fn destroy_device(device: ash::Device) {
+//!     for live_resource in ashpan::leak::report_live_resources() {
+//!         eprintln!("Leaked {live_resource}");
+//!     }
+//!     device.destroy_device(None);
+//! }
+//! ```
+//!
+//! Resources extracted with [`GuardedResource::take`] stay registered (and are marked as
+//! [handed off](LiveResource::handed_off)) until they're destroyed, or re-registered if they're
+//! guarded again. Resources that were left alone because their destroyer wasn't available (e.g.
+//! a [`DeviceContext`] missing a loader) also stay registered, and are marked as
+//! [`destroyer_unavailable`](LiveResource::destroyer_unavailable).
+//!
+//! The registry is global and keyed by object type and raw handle, so handles of the same type
+//! from different devices can collide. Capturing backtraces is slow; this feature is intended for
+//! debug builds.
+//!
+//! [`DeviceContext`]: crate::DeviceContext
+//! [`GuardedResource`]: crate::GuardedResource
+//! [`GuardedResource::take`]: crate::GuardedResource::take
+
+use std::any::type_name;
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+
+use crate::Destroyable;
+
+/// A Vulkan object that was guarded and hasn't been destroyed yet
+#[derive(Clone, Debug)]
+pub struct LiveResource {
+    /// Type of the Vulkan object
+    pub object_type: vk::ObjectType,
+    /// Raw handle of the Vulkan object
+    pub handle: u64,
+    /// Name of the guarded type that the object belonged to, as returned by [`type_name`]
+    pub type_name: &'static str,
+    /// Whether the object was extracted with [`GuardedResource::take`](crate::GuardedResource::take)
+    pub handed_off: bool,
+    /// Whether destroying the object was skipped because its destroyer wasn't available
+    pub destroyer_unavailable: bool,
+    /// Where the object was guarded
+    pub backtrace: Arc<Backtrace>,
+    sequence: u64,
+}
+
+impl fmt::Display for LiveResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:#x} from {}",
+            self.object_type, self.handle, self.type_name
+        )?;
+        if self.handed_off {
+            write!(f, " (handed off)")?;
+        }
+        if self.destroyer_unavailable {
+            write!(f, " (destroyer unavailable)")?;
+        }
+        write!(f, ", guarded at:\n{}", self.backtrace)
+    }
+}
+
+/// Returns all registered resources that haven't been destroyed yet, in the order they were
+/// registered.
+pub fn report_live_resources() -> Vec<LiveResource> {
+    let mut live_resources: Vec<_> = REGISTRY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|registry| registry.live_resources.values().cloned().collect())
+        .unwrap_or_default();
+    live_resources.sort_by_key(|live_resource| live_resource.sequence);
+    live_resources
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+#[derive(Default)]
+struct Registry {
+    next_sequence: u64,
+    live_resources: HashMap<(vk::ObjectType, u64), LiveResource>,
+}
+
+pub(crate) fn register<Resource: Destroyable>(resource: &Resource) {
+    let backtrace = Arc::new(Backtrace::force_capture());
+    let mut registry = REGISTRY.lock().unwrap();
+    let registry = registry.get_or_insert_with(Default::default);
+
+    resource.visit_handles(&mut |object_type, handle| {
+        if handle == 0 {
+            return;
+        }
+        let sequence = registry.next_sequence;
+        registry.next_sequence += 1;
+        registry.live_resources.insert(
+            (object_type, handle),
+            LiveResource {
+                object_type,
+                handle,
+                type_name: type_name::<Resource>(),
+                handed_off: false,
+                destroyer_unavailable: false,
+                backtrace: backtrace.clone(),
+                sequence,
+            },
+        );
+    });
+}
+
+pub(crate) fn hand_off(resource: &impl Destroyable) {
+    if let Some(registry) = REGISTRY.lock().unwrap().as_mut() {
+        resource.visit_handles(&mut |object_type, handle| {
+            if let Some(live_resource) = registry.live_resources.get_mut(&(object_type, handle)) {
+                live_resource.handed_off = true;
+            }
+        });
+    }
+}
+
+pub(crate) fn flag_destroyer_unavailable(resource: &(impl Destroyable + ?Sized)) {
+    if let Some(registry) = REGISTRY.lock().unwrap().as_mut() {
+        resource.visit_handles(&mut |object_type, handle| {
+            if let Some(live_resource) = registry.live_resources.get_mut(&(object_type, handle)) {
+                live_resource.destroyer_unavailable = true;
+            }
+        });
+    }
+}
+
+pub(crate) fn unregister(object_type: vk::ObjectType, handle: u64) {
+    if let Some(registry) = REGISTRY.lock().unwrap().as_mut() {
+        registry.live_resources.remove(&(object_type, handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use ash::{
+        prelude::VkResult,
+        vk::{self, Handle},
+    };
+
+    use super::report_live_resources;
+    use crate::{mock, DeviceExt, Guarded, Reversed};
+
+    fn live_resource(object_type: vk::ObjectType, handle: u64) -> Option<(bool, &'static str)> {
+        report_live_resources()
+            .into_iter()
+            .find(|live_resource| {
+                live_resource.object_type == object_type && live_resource.handle == handle
+            })
+            .map(|live_resource| (live_resource.handed_off, live_resource.type_name))
+    }
+
+    #[test]
+    fn guarded_resources_are_live_until_dropped() {
+        let (_instance, device) = mock::instance_and_device();
+        let buffer = unsafe { (&device).create_guarded_buffer(&Default::default(), None) }.unwrap();
+        let handle = buffer.as_raw();
+
+        assert_eq!(
+            live_resource(vk::ObjectType::BUFFER, handle),
+            Some((false, std::any::type_name::<vk::Buffer>()))
+        );
+        drop(buffer);
+        assert_eq!(live_resource(vk::ObjectType::BUFFER, handle), None);
+    }
+
+    #[test]
+    fn taken_resources_are_handed_off_until_destroyed() {
+        let (_instance, device) = mock::instance_and_device();
+        let buffer = unsafe { (&device).create_guarded_buffer(&Default::default(), None) }
+            .unwrap()
+            .take();
+        assert!(matches!(
+            live_resource(vk::ObjectType::BUFFER, buffer.as_raw()),
+            Some((true, _))
+        ));
+
+        let buffers: Guarded<_> = unsafe { Guarded::new(vec![buffer], &device, None) };
+        assert_eq!(
+            live_resource(vk::ObjectType::BUFFER, buffer.as_raw()),
+            Some((false, std::any::type_name::<Vec<vk::Buffer>>()))
+        );
+
+        drop(buffers);
+        assert_eq!(live_resource(vk::ObjectType::BUFFER, buffer.as_raw()), None);
+    }
+
+    #[test]
+    fn resources_guarded_from_iterators_are_live_until_dropped() {
+        let (_instance, device) = mock::instance_and_device();
+        let create_buffer = |_| unsafe { device.create_buffer(&Default::default(), None) };
+        let buffers: Guarded<Vec<_>> =
+            unsafe { Guarded::try_new_from((0..2).map(create_buffer), &device, None) }.unwrap();
+        let reversed_buffers: Guarded<Reversed<Vec<_>>> =
+            unsafe { Guarded::try_new_reversed_from((0..2).map(create_buffer), &device, None) }
+                .unwrap();
+        let handles: Vec<_> = buffers
+            .iter()
+            .chain(reversed_buffers.iter())
+            .map(|buffer| buffer.as_raw())
+            .collect();
+
+        for &handle in &handles[..2] {
+            assert_eq!(
+                live_resource(vk::ObjectType::BUFFER, handle),
+                Some((false, std::any::type_name::<Vec<vk::Buffer>>()))
+            );
+        }
+        for &handle in &handles[2..] {
+            assert_eq!(
+                live_resource(vk::ObjectType::BUFFER, handle),
+                Some((false, std::any::type_name::<Reversed<Vec<vk::Buffer>>>()))
+            );
+        }
+
+        drop(buffers);
+        drop(reversed_buffers);
+        for handle in handles {
+            assert_eq!(live_resource(vk::ObjectType::BUFFER, handle), None);
+        }
+    }
+
+    #[test]
+    fn resources_guarded_in_maps_are_live_until_dropped() {
+        let (_instance, device) = mock::instance_and_device();
+        let create_buffer = |key| -> VkResult<_> {
+            let buffer = unsafe { device.create_buffer(&Default::default(), None) }?;
+            Ok((key, buffer))
+        };
+        let hash_map: Guarded<HashMap<_, _>> =
+            unsafe { Guarded::try_new_hash_map_from((0..2).map(create_buffer), &device, None) }
+                .unwrap();
+        let btree_map: Guarded<BTreeMap<_, _>> =
+            unsafe { Guarded::try_new_btree_map_from((0..2).map(create_buffer), &device, None) }
+                .unwrap();
+        let hash_map_handles: Vec<_> = hash_map.values().map(|buffer| buffer.as_raw()).collect();
+        let btree_map_handles: Vec<_> = btree_map.values().map(|buffer| buffer.as_raw()).collect();
+
+        for &handle in &hash_map_handles {
+            assert_eq!(
+                live_resource(vk::ObjectType::BUFFER, handle),
+                Some((false, std::any::type_name::<HashMap<i32, vk::Buffer>>()))
+            );
+        }
+        for &handle in &btree_map_handles {
+            assert_eq!(
+                live_resource(vk::ObjectType::BUFFER, handle),
+                Some((false, std::any::type_name::<BTreeMap<i32, vk::Buffer>>()))
+            );
+        }
+
+        drop(hash_map);
+        drop(btree_map);
+        for handle in hash_map_handles.into_iter().chain(btree_map_handles) {
+            assert_eq!(live_resource(vk::ObjectType::BUFFER, handle), None);
+        }
+    }
+}
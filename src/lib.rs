@@ -4,9 +4,35 @@
 //! * [`Guarded`]/[`GuardedResource`] is essentially a
 //!   [`ScopeGuard`](https://docs.rs/scopeguard/1.1.0/scopeguard/struct.ScopeGuard.html)
 //!   that selects an appropriate destructor automatically.
+//! * [`GuardScope`] guards many resources at once, destroying them in reverse order.
+//! * [`DynGuarded`] erases the types of a [`GuardedResource`], so that unrelated guards can be
+//!   stored in the same collection.
 //! * [`DeviceExt`] (along with [`EntryExt`] and [`InstanceExt`]) provide convenience methods to
 //!   create resources and wrap them in [`GuardedResource`]s.
-//! * [`Destroyable`] allows you to extend the behavior of [`GuardedResource`].
+//! * [`SwapchainExt`], [`SurfaceExt`], [`DebugUtilsExt`], etc. do the same for resources
+//!   created by [`ash::extensions`]. Extensions without an [`ash`] loader are reached through
+//!   raw function tables via [`DeviceFn`] and [`PhysicalDeviceFn`].
+//! * [`Recording`] and the scopes begun via [`CommandScope`] pair command buffer recording,
+//!   render passes, queries and debug labels with their matching end commands.
+//! * [`DeviceExt::create_guarded_bound_buffer`] and
+//!   [`DeviceExt::create_guarded_bound_image`] create a buffer or image along with memory from a
+//!   type picked by [`MemoryTypeSelector`], guarded together as a [`BoundBuffer`] or
+//!   [`BoundImage`].
+//! * [`MappedMemory`] maps device memory as a typed slice and unmaps it when dropped.
+//! * [`PipelineCacheFile`] loads pipeline cache data that matches the device and saves it
+//!   atomically.
+//! * [`DeletionQueue`] defers destruction of guarded resources until in-flight GPU work that
+//!   uses them has completed. Its
+//!   [`submit_one_shot`](DeletionQueue::submit_one_shot) records, submits and waits for a
+//!   one-shot command buffer, such as for uploads.
+//! * [`TrackingAllocator`] provides [`vk::AllocationCallbacks`](ash::vk::AllocationCallbacks)
+//!   that track host memory usage.
+//! * [`VulkanContext`] owns the entry, instance, device and friends, and destroys them in the
+//!   right order.
+//! * [`DeviceContext`] bundles a device with extension loaders, so that structs mixing core and
+//!   extension resources can be guarded as one unit.
+//! * [`Destroyable`] allows you to extend the behavior of [`GuardedResource`]. With the `derive`
+//!   feature, it can be derived for application-specific structs.
 //!
 //! # Introduction
 //!
@@ -175,45 +201,131 @@
 //!     Ok(Guarded::new(resources, device, None))
 //! }
 //! ```
+//!
+//! With the `derive` feature enabled, the above [`Destroyable`] implementation can be written as:
+//!
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use ash::vk;
+//! use ashpan::Destroyable;
+//!
+//! #[derive(Destroyable)]
+//! struct Resources {
+//!     render_pass: vk::RenderPass,
+//!     pipeline_layout: vk::PipelineLayout,
+//!     pipeline: vk::Pipeline,
+//! }
+//! ```
 
+mod allocator;
+mod bound;
+mod context;
+mod deferred;
 mod destroy;
 mod device;
+mod dynamic;
 mod entry;
+mod extensions;
 mod guarded;
 mod instance;
+#[cfg(feature = "leak-tracking")]
+pub mod leak;
+mod mapped;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod pipeline_cache;
+mod pool;
+mod raw;
+mod ray_tracing;
+mod recording;
+mod scope;
+mod submit;
+mod vulkan_context;
+mod wait;
 
-pub use destroy::Destroyable;
+pub use allocator::TrackingAllocator;
+#[cfg(feature = "derive")]
+pub use ashpan_derive::Destroyable;
+pub use bound::{BoundBuffer, BoundImage, MemoryTypeSelector};
+pub use context::{DestroyerSource, DeviceContext};
+pub use deferred::DeletionQueue;
+pub use destroy::{Destroyable, Reversed};
 pub use device::DeviceExt;
+pub use dynamic::{DynDestroyable, DynGuarded};
 pub use entry::EntryExt;
+pub use extensions::{
+    AccelerationStructureExt, BinaryImportNvxExt, DebugUtilsExt, DeferredHostOperationsExt,
+    DeviceGeneratedCommandsNvExt, DirectModeDisplayExt, DisplayExt, PerformanceQueryIntelExt,
+    PrivateDataExt, RayTracingNvExt, SurfaceExt, SwapchainExt, ValidationCacheExt, VideoQueueExt,
+};
 pub use guarded::{Guarded, GuardedResource};
 pub use instance::InstanceExt;
+pub use mapped::MappedMemory;
+pub use pipeline_cache::PipelineCacheFile;
+pub use pool::{PooledCommandBuffers, PooledDescriptorSets};
+pub use raw::{DeviceFn, FnTable, PhysicalDeviceFn};
+pub use ray_tracing::{DeferredRayTracingPipelines, RayTracingPipelineExt};
+pub use recording::{
+    CommandScope, DebugLabelScope, QueryScope, Recording, RenderPassScope, RenderingScope,
+};
+pub use scope::{GuardScope, Scoped};
+pub use vulkan_context::{VulkanContext, VulkanContextBuilder};
+pub use wait::{WaitForFences, WaitIdle};
+
+#[doc(hidden)]
+pub mod __private {
+    // Used by ashpan-derive
+    pub use crate::context::destroyer_unavailable;
+    pub use ash::vk;
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::{DeviceExt, EntryExt, InstanceExt};
+    use ash::vk;
 
-    #[test]
-    fn sanity_check_extension_trait_compilation() {
-        if true {
-            return;
-        }
+    use crate::{mock, DeviceExt, EntryExt, InstanceExt};
 
-        // Circumvent Rust's knowledge that unimplemented!() never returns.
-        fn unimplemented<T>() -> T {
-            unimplemented!()
-        }
+    #[test]
+    fn guarded_instance_device_and_semaphore_are_destroyed() {
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let (instance_handle, device_handle, semaphore_handle);
 
         unsafe {
-            let entry = ash::Entry::load().unwrap();
-            let instance = entry
-                .create_guarded_instance(unimplemented(), None)
+            let instance = mock::entry()
+                .create_guarded_instance(&Default::default(), Some(&allocation_callbacks))
                 .unwrap();
             let device = instance
-                .create_guarded_device(unimplemented(), unimplemented(), None)
+                .create_guarded_device(
+                    vk::PhysicalDevice::null(),
+                    &Default::default(),
+                    Some(&allocation_callbacks),
+                )
                 .unwrap();
-            let _semaphore = (&*device)
-                .create_guarded_semaphore(unimplemented(), None)
+            let semaphore = (&*device)
+                .create_guarded_semaphore(&Default::default(), Some(&allocation_callbacks))
                 .unwrap();
-        };
+
+            instance_handle = instance.handle();
+            device_handle = device.handle();
+            semaphore_handle = *semaphore;
+        }
+
+        let callbacks = Some(&allocation_callbacks);
+        assert_eq!(
+            mock::calls(&instance_handle),
+            [
+                mock::Call::new("vkCreateInstance", instance_handle, callbacks),
+                mock::Call::new("vkDestroyInstance", instance_handle, callbacks),
+            ]
+        );
+        assert_eq!(
+            mock::calls(&device_handle),
+            [
+                mock::Call::new("vkCreateDevice", device_handle, callbacks),
+                mock::Call::new("vkCreateSemaphore", semaphore_handle, callbacks),
+                mock::Call::new("vkDestroySemaphore", semaphore_handle, callbacks),
+                mock::Call::new("vkDestroyDevice", device_handle, callbacks),
+            ]
+        );
     }
 }
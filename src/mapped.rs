@@ -0,0 +1,274 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::{Deref, DerefMut};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::GuardedResource;
+
+/// Host mapping of a [`vk::DeviceMemory`] that is unmapped when dropped
+///
+/// Pairing [`map_memory`](ash::Device::map_memory) with
+/// [`unmap_memory`](ash::Device::unmap_memory) by hand leaves the memory mapped after an early
+/// return. A [`MappedMemory`] mutably borrows the memory for as long as it's mapped and exposes
+/// `len` elements of type `T`, starting `offset` bytes into the memory, as a `&mut [T]`:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{Guarded, MappedMemory};
+///
+/// unsafe fn upload_vertices(
+///     memory: &mut Guarded<vk::DeviceMemory>,
+///     allocation_size: vk::DeviceSize,
+///     vertices: &[[f32; 3]],
+/// ) -> VkResult<()> {
+///     let mut mapped = MappedMemory::new(memory, allocation_size, 0, vertices.len())?;
+///     mapped.copy_from_slice(vertices);
+///     // Only needed if the memory isn't HOST_COHERENT
+///     mapped.flush()
+/// }
+/// ```
+///
+/// The whole allocation is mapped, so [`flush`](Self::flush) and
+/// [`invalidate`](Self::invalidate) cover all of it, which satisfies the
+/// `nonCoherentAtomSize` alignment requirements of non-coherent memory. Memory can only be mapped
+/// once at a time, which the mutable borrow enforces:
+///
+/// ```compile_fail
+/// # use ash::vk;
+/// # use ashpan::{Guarded, MappedMemory};
+/// # unsafe fn map_twice(memory: &mut Guarded<vk::DeviceMemory>) {
+/// let vertices = MappedMemory::<[f32; 3]>::new(memory, 1024, 0, 16).unwrap();
+/// let indices = MappedMemory::<u16>::new(memory, 1024, 512, 64).unwrap();
+/// # drop((vertices, indices));
+/// # }
+/// ```
+pub struct MappedMemory<'m, T> {
+    device: &'m ash::Device,
+    memory: vk::DeviceMemory,
+    data: *mut T,
+    len: usize,
+    _memory: PhantomData<&'m mut [T]>,
+}
+
+impl<'m, T: Copy> MappedMemory<'m, T> {
+    /// Maps `memory`, which is `allocation_size` bytes large, and exposes `len` elements
+    /// starting `offset` bytes in.
+    ///
+    /// # Safety
+    ///
+    /// `allocation_size` must be the size `memory` was allocated with, and `memory` must be
+    /// `HOST_VISIBLE`. Any bit pattern in the range must be a valid `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`map_memory`](ash::Device::map_memory).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range doesn't fit in `allocation_size` bytes, or if `offset` isn't aligned
+    /// for `T`.
+    pub unsafe fn new<'g, Destroyer>(
+        memory: &'m mut GuardedResource<'g, vk::DeviceMemory, Destroyer>,
+        allocation_size: vk::DeviceSize,
+        offset: vk::DeviceSize,
+        len: usize,
+    ) -> VkResult<Self>
+    where
+        Destroyer: Deref<Target = ash::Device>,
+    {
+        Self::from_raw(
+            memory.destroyer_ref(),
+            **memory,
+            allocation_size,
+            offset,
+            len,
+        )
+    }
+
+    /// Same as [`new`](Self::new), but for memory that isn't guarded.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`](Self::new). Additionally, `memory` must have been allocated by `device`,
+    /// must not already be mapped, and must not be freed or mapped again while mapped.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`new`](Self::new).
+    ///
+    /// # Panics
+    ///
+    /// Same as [`new`](Self::new).
+    pub unsafe fn from_raw(
+        device: &'m ash::Device,
+        memory: vk::DeviceMemory,
+        allocation_size: vk::DeviceSize,
+        offset: vk::DeviceSize,
+        len: usize,
+    ) -> VkResult<Self> {
+        let end = (len as u128) * (size_of::<T>() as u128) + u128::from(offset);
+        assert!(
+            end <= u128::from(allocation_size),
+            "mapped range ends at byte {end}, beyond the allocation size of {allocation_size}"
+        );
+        assert_eq!(
+            offset % align_of::<T>() as u64,
+            0,
+            "mapped range offset isn't aligned for the element type"
+        );
+
+        let base = device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())?;
+        let mapped = Self {
+            device,
+            memory,
+            data: base.cast::<u8>().add(offset as usize).cast(),
+            len,
+            _memory: PhantomData,
+        };
+        // Holds as long as minMemoryMapAlignment is at least align_of::<T>(). If it doesn't,
+        // dropping `mapped` unmaps the memory.
+        assert_eq!(
+            mapped.data as usize % align_of::<T>(),
+            0,
+            "mapped pointer isn't aligned for the element type"
+        );
+        Ok(mapped)
+    }
+}
+
+impl<'m, T> MappedMemory<'m, T> {
+    /// Returns the mapped memory.
+    pub fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    /// Makes host writes to the memory visible to the device. Only needed if the memory isn't
+    /// `HOST_COHERENT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from
+    /// [`flush_mapped_memory_ranges`](ash::Device::flush_mapped_memory_ranges).
+    pub fn flush(&self) -> VkResult<()> {
+        unsafe {
+            self.device
+                .flush_mapped_memory_ranges(&[self.whole_range()])
+        }
+    }
+
+    /// Makes device writes to the memory visible to the host. Only needed if the memory isn't
+    /// `HOST_COHERENT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from
+    /// [`invalidate_mapped_memory_ranges`](ash::Device::invalidate_mapped_memory_ranges).
+    pub fn invalidate(&mut self) -> VkResult<()> {
+        unsafe {
+            self.device
+                .invalidate_mapped_memory_ranges(&[self.whole_range()])
+        }
+    }
+
+    fn whole_range(&self) -> vk::MappedMemoryRange {
+        vk::MappedMemoryRange::builder()
+            .memory(self.memory)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .build()
+    }
+}
+
+impl<'m, T> Deref for MappedMemory<'m, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+impl<'m, T> DerefMut for MappedMemory<'m, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.data, self.len) }
+    }
+}
+
+impl<'m, T> Drop for MappedMemory<'m, T> {
+    fn drop(&mut self) {
+        unsafe { self.device.unmap_memory(self.memory) }
+    }
+}
+
+impl<'m, T> fmt::Debug for MappedMemory<'m, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedMemory")
+            .field("device", &self.device.handle())
+            .field("memory", &self.memory)
+            .field("data", &self.data)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::{mock, DeviceExt, MappedMemory};
+
+    #[test]
+    fn memory_is_unmapped_when_dropped() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocate_info = vk::MemoryAllocateInfo::builder().allocation_size(256);
+        let mut memory =
+            unsafe { (&device).allocate_guarded_memory(&allocate_info, None) }.unwrap();
+
+        {
+            let mut mapped = unsafe { MappedMemory::<u32>::new(&mut memory, 256, 64, 4) }.unwrap();
+            mapped.copy_from_slice(&[1, 2, 3, 4]);
+            mapped.flush().unwrap();
+        }
+        {
+            let mut mapped = unsafe { MappedMemory::<u32>::new(&mut memory, 256, 68, 2) }.unwrap();
+            mapped.invalidate().unwrap();
+            assert_eq!(*mapped, [2, 3]);
+        }
+
+        let handle = *memory;
+        assert_eq!(
+            mock::calls(&device)[2..],
+            [
+                mock::Call::new("vkMapMemory", handle, None),
+                mock::Call::new("vkFlushMappedMemoryRanges", handle, None),
+                mock::Call::new("vkUnmapMemory", handle, None),
+                mock::Call::new("vkMapMemory", handle, None),
+                mock::Call::new("vkInvalidateMappedMemoryRanges", handle, None),
+                mock::Call::new("vkUnmapMemory", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic = "mapped range ends at byte 260, beyond the allocation size of 256"]
+    fn ranges_beyond_the_allocation_panic() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocate_info = vk::MemoryAllocateInfo::builder().allocation_size(256);
+        let mut memory =
+            unsafe { (&device).allocate_guarded_memory(&allocate_info, None) }.unwrap();
+
+        let _ = unsafe { MappedMemory::<u32>::new(&mut memory, 256, 4, 64) };
+    }
+
+    #[test]
+    #[should_panic = "mapped range offset isn't aligned for the element type"]
+    fn misaligned_offsets_panic() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocate_info = vk::MemoryAllocateInfo::builder().allocation_size(256);
+        let mut memory =
+            unsafe { (&device).allocate_guarded_memory(&allocate_info, None) }.unwrap();
+
+        let _ = unsafe { MappedMemory::<u32>::new(&mut memory, 256, 2, 1) };
+    }
+}
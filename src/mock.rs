@@ -0,0 +1,1407 @@
+//! Stub Vulkan implementation for testing without a GPU
+//!
+//! Available with the `mock` feature. [`entry()`] returns a real [`ash::Entry`] whose function
+//! pointers are stubs; instances, devices and extension loaders created from it are backed by
+//! stubs as well. Every stub that creates, allocates, destroys or frees something records a
+//! [`Call`] that can be inspected with [`calls()`]:
+//!
+//! ```
+//! use ash::vk;
+//! use ashpan::{mock, DeviceExt};
+//!
+//! let (_instance, device) = mock::instance_and_device();
+//! let allocation_callbacks = vk::AllocationCallbacks::default();
+//!
+//! let buffer = unsafe {
+//!     (&device).create_guarded_buffer(&Default::default(), Some(&allocation_callbacks))
+//! }
+//! .unwrap();
+//! let handle = *buffer;
+//! drop(buffer);
+//!
+//! assert_eq!(
+//!     mock::calls(&device)[1..],
+//!     [
+//!         mock::Call::new("vkCreateBuffer", handle, Some(&allocation_callbacks)),
+//!         mock::Call::new("vkDestroyBuffer", handle, Some(&allocation_callbacks)),
+//!     ]
+//! );
+//! ```
+//!
+//! Calls are recorded globally, keyed by the dispatchable handle (instance, physical device,
+//! device, command buffer or queue) they were made through, so tests using separate instances
+//! and devices can run in parallel. Creating an instance or device is recorded under the new
+//! handle. Use [`physical_device()`] for physical devices that are distinct from each other.
+//!
+//! Fences are unsignaled until passed to [`signal_fence()`] or submitted to a [`queue()`] that
+//! isn't stalled. Waiting for fences of devices passed to [`lose_device()`] returns
+//! [`vk::Result::ERROR_DEVICE_LOST`], and [`fail_fence_waits()`] does the same for other errors.
+//! Deferred operations stay pending until joined, unless the join was made to fail with
+//! [`fail_next_join()`]. Device memory is backed by zeroed host memory, whose contents persist
+//! across mappings until the memory is freed. Pipeline caches hold their initial data, and
+//! merging appends the source caches' data to the destination cache's.
+//!
+//! Functions without a stub aren't loaded, so calling them panics.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use ash::vk::{self, Handle};
+
+/// A recorded call to a stub Vulkan function
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    /// Name of the Vulkan function, e.g. `"vkCreateBuffer"`
+    pub function: &'static str,
+    /// Raw handle that was created or destroyed
+    pub handle: u64,
+    /// Raw handle of the pool that `handle` was allocated from or freed to, if any
+    pub pool: Option<u64>,
+    /// Allocation callbacks that were passed to the function
+    pub allocation_callbacks: Option<*const vk::AllocationCallbacks>,
+    /// Debug name that `handle` was given, if any
+    pub name: Option<String>,
+}
+
+impl Call {
+    /// Creates a [`Call`] that doesn't involve a pool.
+    pub fn new(
+        function: &'static str,
+        handle: impl Handle,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) -> Self {
+        Self {
+            function,
+            handle: handle.as_raw(),
+            pool: None,
+            allocation_callbacks: allocation_callbacks.map(|a| a as _),
+            name: None,
+        }
+    }
+
+    /// Creates a [`Call`] that allocates `handle` from `pool` or frees it to `pool`.
+    pub fn pooled(function: &'static str, handle: impl Handle, pool: impl Handle) -> Self {
+        Self {
+            function,
+            handle: handle.as_raw(),
+            pool: Some(pool.as_raw()),
+            allocation_callbacks: None,
+            name: None,
+        }
+    }
+
+    /// Creates a [`Call`] that gives `handle` a debug name.
+    pub fn named(function: &'static str, handle: impl Handle, name: &str) -> Self {
+        Self {
+            function,
+            handle: handle.as_raw(),
+            pool: None,
+            allocation_callbacks: None,
+            name: Some(name.to_owned()),
+        }
+    }
+}
+
+// Raw pointers are only compared, never dereferenced.
+unsafe impl Send for Call {}
+
+/// Something that calls are made through, i.e. an instance, physical device, device, command
+/// buffer or queue
+pub trait Dispatcher {
+    /// Returns the raw dispatchable handle.
+    fn raw_dispatch_handle(&self) -> u64;
+}
+
+impl Dispatcher for ash::Instance {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.handle().as_raw()
+    }
+}
+
+impl Dispatcher for ash::Device {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.handle().as_raw()
+    }
+}
+
+impl Dispatcher for vk::Instance {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.as_raw()
+    }
+}
+
+impl Dispatcher for vk::Device {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.as_raw()
+    }
+}
+
+impl Dispatcher for vk::PhysicalDevice {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.as_raw()
+    }
+}
+
+impl Dispatcher for vk::CommandBuffer {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.as_raw()
+    }
+}
+
+impl Dispatcher for vk::Queue {
+    fn raw_dispatch_handle(&self) -> u64 {
+        self.as_raw()
+    }
+}
+
+/// Returns all calls made through `dispatcher` so far, in order.
+pub fn calls(dispatcher: &impl Dispatcher) -> Vec<Call> {
+    let dispatcher = dispatcher.raw_dispatch_handle();
+    CALLS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|calls| calls.get(&dispatcher))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Returns an [`ash::Entry`] backed by stubs.
+pub fn entry() -> ash::Entry {
+    let static_fn = vk::StaticFn {
+        get_instance_proc_addr: vkGetInstanceProcAddr,
+    };
+    unsafe { ash::Entry::from_static_fn(static_fn) }
+}
+
+/// Returns a stub [`ash::Instance`] and a stub [`ash::Device`] created from it.
+pub fn instance_and_device() -> (ash::Instance, ash::Device) {
+    unsafe {
+        let instance = entry().create_instance(&Default::default(), None).unwrap();
+        let device = instance
+            .create_device(vk::PhysicalDevice::null(), &Default::default(), None)
+            .unwrap();
+        (instance, device)
+    }
+}
+
+/// Returns a stub [`vk::PhysicalDevice`] that is distinct from all others, so that calls made
+/// through it can be told apart.
+pub fn physical_device() -> vk::PhysicalDevice {
+    next_handle()
+}
+
+/// Returns a stub [`vk::Queue`] that is distinct from all others. Work submitted to it
+/// completes immediately, signaling the submission's fence, unless it's passed to
+/// [`stall_queue()`].
+pub fn queue() -> vk::Queue {
+    next_handle()
+}
+
+/// Makes work submitted to `queue` never complete, so that the submission's fence stays
+/// unsignaled until passed to [`signal_fence()`].
+pub fn stall_queue(queue: vk::Queue) {
+    STALLED_QUEUES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .insert(queue.as_raw());
+}
+
+/// Marks `device` as lost, so that waiting for or querying its fences returns
+/// [`vk::Result::ERROR_DEVICE_LOST`].
+pub fn lose_device(device: &ash::Device) {
+    fail_fence_waits(device, vk::Result::ERROR_DEVICE_LOST);
+}
+
+/// Makes waiting for or querying `device`'s fences return `result`, e.g.
+/// [`vk::Result::ERROR_OUT_OF_HOST_MEMORY`].
+pub fn fail_fence_waits(device: &ash::Device, result: vk::Result) {
+    FENCE_WAIT_FAILURES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .insert(device.handle().as_raw(), result);
+}
+
+/// Makes the next join of `deferred_operation` return `result`, e.g.
+/// [`vk::Result::ERROR_OUT_OF_HOST_MEMORY`], without completing it.
+pub fn fail_next_join(deferred_operation: vk::DeferredOperationKHR, result: vk::Result) {
+    JOIN_FAILURES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .insert(deferred_operation.as_raw(), result);
+}
+
+/// Signals `fence`, so that querying its status returns [`vk::Result::SUCCESS`].
+pub fn signal_fence(fence: vk::Fence) {
+    SIGNALED_FENCES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .insert(fence.as_raw());
+}
+
+static CALLS: Mutex<Option<HashMap<u64, Vec<Call>>>> = Mutex::new(None);
+static SIGNALED_FENCES: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+static STALLED_QUEUES: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+static FENCE_WAIT_FAILURES: Mutex<Option<HashMap<u64, vk::Result>>> = Mutex::new(None);
+static PENDING_DEFERRED_OPERATIONS: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+static JOIN_FAILURES: Mutex<Option<HashMap<u64, vk::Result>>> = Mutex::new(None);
+static PIPELINE_CACHES: Mutex<Option<HashMap<u64, Vec<u8>>>> = Mutex::new(None);
+static MEMORY: Mutex<Option<HashMap<u64, Box<[MemoryBlock]>>>> = Mutex::new(None);
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(0x1000);
+
+// Mapped pointers are aligned like on real implementations, whose minMemoryMapAlignment is at
+// least 64. The contents are only accessed through mapped pointers.
+#[derive(Clone, Copy)]
+#[repr(align(64))]
+struct MemoryBlock(#[allow(dead_code)] [u8; 64]);
+
+fn next_handle<H: Handle>() -> H {
+    H::from_raw(NEXT_HANDLE.fetch_add(1, Ordering::Relaxed))
+}
+
+fn record(dispatcher: impl Handle, call: Call) {
+    CALLS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Default::default)
+        .entry(dispatcher.as_raw())
+        .or_default()
+        .push(call);
+}
+
+fn fence_wait_failure(device: vk::Device) -> Option<vk::Result> {
+    FENCE_WAIT_FAILURES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|failures| failures.get(&device.as_raw()).copied())
+}
+
+unsafe fn record_handle(
+    dispatcher: impl Handle,
+    function: &'static str,
+    handle: impl Handle,
+    allocation_callbacks: *const vk::AllocationCallbacks,
+) {
+    record(
+        dispatcher,
+        Call::new(function, handle, allocation_callbacks.as_ref()),
+    );
+}
+
+macro_rules! create {
+    ($name:ident, $Dispatcher:ty, $CreateInfo:ty, $Resource:ty) => {
+        pub unsafe extern "system" fn $name(
+            dispatcher: $Dispatcher,
+            _create_info: *const $CreateInfo,
+            allocation_callbacks: *const vk::AllocationCallbacks,
+            resource: *mut $Resource,
+        ) -> vk::Result {
+            *resource = next_handle();
+            record_handle(
+                dispatcher,
+                stringify!($name),
+                *resource,
+                allocation_callbacks,
+            );
+            vk::Result::SUCCESS
+        }
+    };
+}
+
+macro_rules! destroy {
+    ($name:ident, $Dispatcher:ty, $Resource:ty) => {
+        pub unsafe extern "system" fn $name(
+            dispatcher: $Dispatcher,
+            resource: $Resource,
+            allocation_callbacks: *const vk::AllocationCallbacks,
+        ) {
+            record_handle(
+                dispatcher,
+                stringify!($name),
+                resource,
+                allocation_callbacks,
+            );
+        }
+    };
+}
+
+macro_rules! command {
+    ($name:ident $(, $Arg:ty)*) => {
+        pub unsafe extern "system" fn $name(command_buffer: vk::CommandBuffer, $(_: $Arg),*) {
+            record_handle(
+                command_buffer,
+                stringify!($name),
+                command_buffer,
+                ptr::null(),
+            );
+        }
+    };
+}
+
+macro_rules! create_pipelines {
+    ($name:ident, $CreateInfo:ty) => {
+        pub unsafe extern "system" fn $name(
+            device: vk::Device,
+            _pipeline_cache: vk::PipelineCache,
+            create_info_count: u32,
+            _create_infos: *const $CreateInfo,
+            allocation_callbacks: *const vk::AllocationCallbacks,
+            pipelines: *mut vk::Pipeline,
+        ) -> vk::Result {
+            for i in 0..create_info_count as usize {
+                *pipelines.add(i) = next_handle();
+                record_handle(
+                    device,
+                    stringify!($name),
+                    *pipelines.add(i),
+                    allocation_callbacks,
+                );
+            }
+            vk::Result::SUCCESS
+        }
+    };
+}
+
+// Stubs are named after the functions they implement, which is how they're looked up.
+#[allow(non_snake_case)]
+mod stubs {
+    use super::*;
+
+    pub unsafe extern "system" fn vkCreateInstance(
+        _create_info: *const vk::InstanceCreateInfo,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        instance: *mut vk::Instance,
+    ) -> vk::Result {
+        *instance = next_handle();
+        record_handle(
+            *instance,
+            "vkCreateInstance",
+            *instance,
+            allocation_callbacks,
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkCreateDevice(
+        _physical_device: vk::PhysicalDevice,
+        _create_info: *const vk::DeviceCreateInfo,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        device: *mut vk::Device,
+    ) -> vk::Result {
+        *device = next_handle();
+        record_handle(*device, "vkCreateDevice", *device, allocation_callbacks);
+        vk::Result::SUCCESS
+    }
+
+    create!(
+        vkCreateHeadlessSurfaceEXT,
+        vk::Instance,
+        vk::HeadlessSurfaceCreateInfoEXT,
+        vk::SurfaceKHR
+    );
+    create!(
+        vkCreateDebugUtilsMessengerEXT,
+        vk::Instance,
+        vk::DebugUtilsMessengerCreateInfoEXT,
+        vk::DebugUtilsMessengerEXT
+    );
+
+    destroy!(vkDestroySurfaceKHR, vk::Instance, vk::SurfaceKHR);
+    destroy!(
+        vkDestroyDebugUtilsMessengerEXT,
+        vk::Instance,
+        vk::DebugUtilsMessengerEXT
+    );
+
+    pub unsafe extern "system" fn vkDestroyInstance(
+        instance: vk::Instance,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+    ) {
+        record_handle(
+            instance,
+            "vkDestroyInstance",
+            instance,
+            allocation_callbacks,
+        );
+    }
+
+    pub unsafe extern "system" fn vkDestroyDevice(
+        device: vk::Device,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+    ) {
+        record_handle(device, "vkDestroyDevice", device, allocation_callbacks);
+    }
+
+    pub unsafe extern "system" fn vkAllocateMemory(
+        device: vk::Device,
+        allocate_info: *const vk::MemoryAllocateInfo,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        memory: *mut vk::DeviceMemory,
+    ) -> vk::Result {
+        *memory = next_handle();
+        record_handle(device, "vkAllocateMemory", *memory, allocation_callbacks);
+        let blocks = (*allocate_info).allocation_size.div_ceil(64) as usize;
+        MEMORY
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default)
+            .insert(
+                (*memory).as_raw(),
+                vec![MemoryBlock([0; 64]); blocks].into_boxed_slice(),
+            );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkFreeMemory(
+        device: vk::Device,
+        memory: vk::DeviceMemory,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+    ) {
+        record_handle(device, "vkFreeMemory", memory, allocation_callbacks);
+        if let Some(allocations) = MEMORY.lock().unwrap().as_mut() {
+            allocations.remove(&memory.as_raw());
+        }
+    }
+
+    // Every memory type is suitable, and allocations of this size can be mapped.
+    const MEMORY_REQUIREMENTS: vk::MemoryRequirements = vk::MemoryRequirements {
+        size: 256,
+        alignment: 64,
+        memory_type_bits: u32::MAX,
+    };
+
+    pub unsafe extern "system" fn vkGetBufferMemoryRequirements(
+        _device: vk::Device,
+        _buffer: vk::Buffer,
+        memory_requirements: *mut vk::MemoryRequirements,
+    ) {
+        *memory_requirements = MEMORY_REQUIREMENTS;
+    }
+
+    pub unsafe extern "system" fn vkGetImageMemoryRequirements(
+        _device: vk::Device,
+        _image: vk::Image,
+        memory_requirements: *mut vk::MemoryRequirements,
+    ) {
+        *memory_requirements = MEMORY_REQUIREMENTS;
+    }
+
+    pub unsafe extern "system" fn vkBindBufferMemory(
+        device: vk::Device,
+        buffer: vk::Buffer,
+        _memory: vk::DeviceMemory,
+        _memory_offset: vk::DeviceSize,
+    ) -> vk::Result {
+        record_handle(device, "vkBindBufferMemory", buffer, ptr::null());
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkBindImageMemory(
+        device: vk::Device,
+        image: vk::Image,
+        _memory: vk::DeviceMemory,
+        _memory_offset: vk::DeviceSize,
+    ) -> vk::Result {
+        record_handle(device, "vkBindImageMemory", image, ptr::null());
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkMapMemory(
+        device: vk::Device,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+        _size: vk::DeviceSize,
+        _flags: vk::MemoryMapFlags,
+        data: *mut *mut c_void,
+    ) -> vk::Result {
+        record_handle(device, "vkMapMemory", memory, ptr::null());
+        let mut allocations = MEMORY.lock().unwrap();
+        let blocks = allocations
+            .as_mut()
+            .and_then(|allocations| allocations.get_mut(&memory.as_raw()))
+            .expect("vkMapMemory called with unknown memory");
+        // The blocks are boxed, so the pointer stays valid while the map is modified.
+        *data = blocks.as_mut_ptr().cast::<u8>().add(offset as usize).cast();
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkUnmapMemory(device: vk::Device, memory: vk::DeviceMemory) {
+        record_handle(device, "vkUnmapMemory", memory, ptr::null());
+    }
+
+    pub unsafe extern "system" fn vkFlushMappedMemoryRanges(
+        device: vk::Device,
+        memory_range_count: u32,
+        memory_ranges: *const vk::MappedMemoryRange,
+    ) -> vk::Result {
+        for i in 0..memory_range_count as usize {
+            let memory = (*memory_ranges.add(i)).memory;
+            record_handle(device, "vkFlushMappedMemoryRanges", memory, ptr::null());
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkInvalidateMappedMemoryRanges(
+        device: vk::Device,
+        memory_range_count: u32,
+        memory_ranges: *const vk::MappedMemoryRange,
+    ) -> vk::Result {
+        for i in 0..memory_range_count as usize {
+            let memory = (*memory_ranges.add(i)).memory;
+            record_handle(
+                device,
+                "vkInvalidateMappedMemoryRanges",
+                memory,
+                ptr::null(),
+            );
+        }
+        vk::Result::SUCCESS
+    }
+
+    create!(vkCreateBuffer, vk::Device, vk::BufferCreateInfo, vk::Buffer);
+    create!(
+        vkCreateBufferView,
+        vk::Device,
+        vk::BufferViewCreateInfo,
+        vk::BufferView
+    );
+    create!(
+        vkCreateCommandPool,
+        vk::Device,
+        vk::CommandPoolCreateInfo,
+        vk::CommandPool
+    );
+    create!(
+        vkCreateDescriptorPool,
+        vk::Device,
+        vk::DescriptorPoolCreateInfo,
+        vk::DescriptorPool
+    );
+    create!(
+        vkCreateDescriptorSetLayout,
+        vk::Device,
+        vk::DescriptorSetLayoutCreateInfo,
+        vk::DescriptorSetLayout
+    );
+    create!(vkCreateEvent, vk::Device, vk::EventCreateInfo, vk::Event);
+    create!(vkCreateFence, vk::Device, vk::FenceCreateInfo, vk::Fence);
+    create!(
+        vkCreateFramebuffer,
+        vk::Device,
+        vk::FramebufferCreateInfo,
+        vk::Framebuffer
+    );
+    create!(vkCreateImage, vk::Device, vk::ImageCreateInfo, vk::Image);
+    create!(
+        vkCreateImageView,
+        vk::Device,
+        vk::ImageViewCreateInfo,
+        vk::ImageView
+    );
+    create!(
+        vkCreatePipelineLayout,
+        vk::Device,
+        vk::PipelineLayoutCreateInfo,
+        vk::PipelineLayout
+    );
+    create!(
+        vkCreateQueryPool,
+        vk::Device,
+        vk::QueryPoolCreateInfo,
+        vk::QueryPool
+    );
+    create!(
+        vkCreateRenderPass,
+        vk::Device,
+        vk::RenderPassCreateInfo,
+        vk::RenderPass
+    );
+    create!(
+        vkCreateRenderPass2,
+        vk::Device,
+        vk::RenderPassCreateInfo2,
+        vk::RenderPass
+    );
+    create!(
+        vkCreateSampler,
+        vk::Device,
+        vk::SamplerCreateInfo,
+        vk::Sampler
+    );
+    create!(
+        vkCreateSemaphore,
+        vk::Device,
+        vk::SemaphoreCreateInfo,
+        vk::Semaphore
+    );
+    create!(
+        vkCreateShaderModule,
+        vk::Device,
+        vk::ShaderModuleCreateInfo,
+        vk::ShaderModule
+    );
+    create!(
+        vkCreateDescriptorUpdateTemplate,
+        vk::Device,
+        vk::DescriptorUpdateTemplateCreateInfo,
+        vk::DescriptorUpdateTemplate
+    );
+    create!(
+        vkCreateSamplerYcbcrConversion,
+        vk::Device,
+        vk::SamplerYcbcrConversionCreateInfo,
+        vk::SamplerYcbcrConversion
+    );
+    create!(
+        vkCreateSwapchainKHR,
+        vk::Device,
+        vk::SwapchainCreateInfoKHR,
+        vk::SwapchainKHR
+    );
+    create!(
+        vkCreateAccelerationStructureKHR,
+        vk::Device,
+        vk::AccelerationStructureCreateInfoKHR,
+        vk::AccelerationStructureKHR
+    );
+    create!(
+        vkCreateAccelerationStructureNV,
+        vk::Device,
+        vk::AccelerationStructureCreateInfoNV,
+        vk::AccelerationStructureNV
+    );
+    create!(
+        vkCreatePrivateDataSlotEXT,
+        vk::Device,
+        vk::PrivateDataSlotCreateInfo,
+        vk::PrivateDataSlot
+    );
+    create!(
+        vkCreateCuModuleNVX,
+        vk::Device,
+        vk::CuModuleCreateInfoNVX,
+        vk::CuModuleNVX
+    );
+    create!(
+        vkCreateCuFunctionNVX,
+        vk::Device,
+        vk::CuFunctionCreateInfoNVX,
+        vk::CuFunctionNVX
+    );
+    create!(
+        vkCreateIndirectCommandsLayoutNV,
+        vk::Device,
+        vk::IndirectCommandsLayoutCreateInfoNV,
+        vk::IndirectCommandsLayoutNV
+    );
+    create!(
+        vkCreateValidationCacheEXT,
+        vk::Device,
+        vk::ValidationCacheCreateInfoEXT,
+        vk::ValidationCacheEXT
+    );
+    create!(
+        vkCreateVideoSessionKHR,
+        vk::Device,
+        vk::VideoSessionCreateInfoKHR,
+        vk::VideoSessionKHR
+    );
+    create!(
+        vkCreateVideoSessionParametersKHR,
+        vk::Device,
+        vk::VideoSessionParametersCreateInfoKHR,
+        vk::VideoSessionParametersKHR
+    );
+    create_pipelines!(vkCreateGraphicsPipelines, vk::GraphicsPipelineCreateInfo);
+    create_pipelines!(vkCreateComputePipelines, vk::ComputePipelineCreateInfo);
+
+    pub unsafe extern "system" fn vkCreateDeferredOperationKHR(
+        device: vk::Device,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        deferred_operation: *mut vk::DeferredOperationKHR,
+    ) -> vk::Result {
+        *deferred_operation = next_handle();
+        record_handle(
+            device,
+            "vkCreateDeferredOperationKHR",
+            *deferred_operation,
+            allocation_callbacks,
+        );
+        vk::Result::SUCCESS
+    }
+
+    // Pipelines with FAIL_ON_PIPELINE_COMPILE_REQUIRED aren't created. Deferred operations stay
+    // pending until joined, although pipelines are written immediately.
+    pub unsafe extern "system" fn vkCreateRayTracingPipelinesKHR(
+        device: vk::Device,
+        deferred_operation: vk::DeferredOperationKHR,
+        _pipeline_cache: vk::PipelineCache,
+        create_info_count: u32,
+        create_infos: *const vk::RayTracingPipelineCreateInfoKHR,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        pipelines: *mut vk::Pipeline,
+    ) -> vk::Result {
+        let mut result = vk::Result::SUCCESS;
+        for i in 0..create_info_count as usize {
+            if (*create_infos.add(i))
+                .flags
+                .contains(vk::PipelineCreateFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED)
+            {
+                *pipelines.add(i) = vk::Pipeline::null();
+                result = vk::Result::PIPELINE_COMPILE_REQUIRED;
+                continue;
+            }
+            *pipelines.add(i) = next_handle();
+            record_handle(
+                device,
+                "vkCreateRayTracingPipelinesKHR",
+                *pipelines.add(i),
+                allocation_callbacks,
+            );
+        }
+
+        if deferred_operation == vk::DeferredOperationKHR::null() || result != vk::Result::SUCCESS {
+            return result;
+        }
+        PENDING_DEFERRED_OPERATIONS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default)
+            .insert(deferred_operation.as_raw());
+        vk::Result::OPERATION_DEFERRED_KHR
+    }
+
+    pub unsafe extern "system" fn vkDeferredOperationJoinKHR(
+        device: vk::Device,
+        operation: vk::DeferredOperationKHR,
+    ) -> vk::Result {
+        record_handle(device, "vkDeferredOperationJoinKHR", operation, ptr::null());
+        let failure = JOIN_FAILURES
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|failures| failures.remove(&operation.as_raw()));
+        if let Some(result) = failure {
+            return result;
+        }
+        if let Some(pending) = PENDING_DEFERRED_OPERATIONS.lock().unwrap().as_mut() {
+            pending.remove(&operation.as_raw());
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkGetDeferredOperationResultKHR(
+        _device: vk::Device,
+        operation: vk::DeferredOperationKHR,
+    ) -> vk::Result {
+        let pending = PENDING_DEFERRED_OPERATIONS
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|pending| pending.contains(&operation.as_raw()));
+        if pending {
+            vk::Result::NOT_READY
+        } else {
+            vk::Result::SUCCESS
+        }
+    }
+
+    destroy!(vkDestroyBuffer, vk::Device, vk::Buffer);
+    destroy!(vkDestroyBufferView, vk::Device, vk::BufferView);
+    destroy!(vkDestroyCommandPool, vk::Device, vk::CommandPool);
+    destroy!(vkDestroyDescriptorPool, vk::Device, vk::DescriptorPool);
+    destroy!(
+        vkDestroyDescriptorSetLayout,
+        vk::Device,
+        vk::DescriptorSetLayout
+    );
+    destroy!(vkDestroyEvent, vk::Device, vk::Event);
+    destroy!(vkDestroyFence, vk::Device, vk::Fence);
+    destroy!(vkDestroyFramebuffer, vk::Device, vk::Framebuffer);
+    destroy!(vkDestroyImage, vk::Device, vk::Image);
+    destroy!(vkDestroyImageView, vk::Device, vk::ImageView);
+    destroy!(vkDestroyPipeline, vk::Device, vk::Pipeline);
+    destroy!(vkDestroyPipelineLayout, vk::Device, vk::PipelineLayout);
+    destroy!(vkDestroyQueryPool, vk::Device, vk::QueryPool);
+    destroy!(vkDestroyRenderPass, vk::Device, vk::RenderPass);
+    destroy!(vkDestroySampler, vk::Device, vk::Sampler);
+    destroy!(vkDestroySemaphore, vk::Device, vk::Semaphore);
+    destroy!(vkDestroyShaderModule, vk::Device, vk::ShaderModule);
+    destroy!(
+        vkDestroyDescriptorUpdateTemplate,
+        vk::Device,
+        vk::DescriptorUpdateTemplate
+    );
+    destroy!(
+        vkDestroySamplerYcbcrConversion,
+        vk::Device,
+        vk::SamplerYcbcrConversion
+    );
+    destroy!(vkDestroySwapchainKHR, vk::Device, vk::SwapchainKHR);
+    destroy!(
+        vkDestroyAccelerationStructureKHR,
+        vk::Device,
+        vk::AccelerationStructureKHR
+    );
+    destroy!(
+        vkDestroyAccelerationStructureNV,
+        vk::Device,
+        vk::AccelerationStructureNV
+    );
+    destroy!(
+        vkDestroyDeferredOperationKHR,
+        vk::Device,
+        vk::DeferredOperationKHR
+    );
+    destroy!(vkDestroyPrivateDataSlotEXT, vk::Device, vk::PrivateDataSlot);
+    destroy!(vkDestroyCuModuleNVX, vk::Device, vk::CuModuleNVX);
+    destroy!(vkDestroyCuFunctionNVX, vk::Device, vk::CuFunctionNVX);
+    destroy!(
+        vkDestroyIndirectCommandsLayoutNV,
+        vk::Device,
+        vk::IndirectCommandsLayoutNV
+    );
+    destroy!(
+        vkDestroyValidationCacheEXT,
+        vk::Device,
+        vk::ValidationCacheEXT
+    );
+    destroy!(vkDestroyVideoSessionKHR, vk::Device, vk::VideoSessionKHR);
+    destroy!(
+        vkDestroyVideoSessionParametersKHR,
+        vk::Device,
+        vk::VideoSessionParametersKHR
+    );
+
+    pub unsafe extern "system" fn vkAcquirePerformanceConfigurationINTEL(
+        device: vk::Device,
+        _acquire_info: *const vk::PerformanceConfigurationAcquireInfoINTEL,
+        configuration: *mut vk::PerformanceConfigurationINTEL,
+    ) -> vk::Result {
+        *configuration = next_handle();
+        record_handle(
+            device,
+            "vkAcquirePerformanceConfigurationINTEL",
+            *configuration,
+            ptr::null(),
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkReleasePerformanceConfigurationINTEL(
+        device: vk::Device,
+        configuration: vk::PerformanceConfigurationINTEL,
+    ) -> vk::Result {
+        record_handle(
+            device,
+            "vkReleasePerformanceConfigurationINTEL",
+            configuration,
+            ptr::null(),
+        );
+        vk::Result::SUCCESS
+    }
+
+    // Displays are recorded under the physical device they belong to.
+
+    pub unsafe extern "system" fn vkCreateDisplayModeKHR(
+        physical_device: vk::PhysicalDevice,
+        _display: vk::DisplayKHR,
+        _create_info: *const vk::DisplayModeCreateInfoKHR,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        mode: *mut vk::DisplayModeKHR,
+    ) -> vk::Result {
+        *mode = next_handle();
+        record_handle(
+            physical_device,
+            "vkCreateDisplayModeKHR",
+            *mode,
+            allocation_callbacks,
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkGetDrmDisplayEXT(
+        _physical_device: vk::PhysicalDevice,
+        _drm_fd: i32,
+        _connector_id: u32,
+        display: *mut vk::DisplayKHR,
+    ) -> vk::Result {
+        *display = next_handle();
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkAcquireDrmDisplayEXT(
+        physical_device: vk::PhysicalDevice,
+        _drm_fd: i32,
+        display: vk::DisplayKHR,
+    ) -> vk::Result {
+        record_handle(
+            physical_device,
+            "vkAcquireDrmDisplayEXT",
+            display,
+            ptr::null(),
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkReleaseDisplayEXT(
+        physical_device: vk::PhysicalDevice,
+        display: vk::DisplayKHR,
+    ) -> vk::Result {
+        record_handle(physical_device, "vkReleaseDisplayEXT", display, ptr::null());
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkDeviceWaitIdle(device: vk::Device) -> vk::Result {
+        record_handle(device, "vkDeviceWaitIdle", device, ptr::null());
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkWaitForFences(
+        device: vk::Device,
+        fence_count: u32,
+        fences: *const vk::Fence,
+        _wait_all: vk::Bool32,
+        _timeout: u64,
+    ) -> vk::Result {
+        let mut signaled = true;
+        for i in 0..fence_count as usize {
+            let fence = *fences.add(i);
+            record_handle(device, "vkWaitForFences", fence, ptr::null());
+            signaled &= SIGNALED_FENCES
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|fences| fences.contains(&fence.as_raw()));
+        }
+        if let Some(result) = fence_wait_failure(device) {
+            result
+        } else if signaled {
+            vk::Result::SUCCESS
+        } else {
+            vk::Result::TIMEOUT
+        }
+    }
+
+    pub unsafe extern "system" fn vkGetFenceStatus(
+        device: vk::Device,
+        fence: vk::Fence,
+    ) -> vk::Result {
+        let signaled = SIGNALED_FENCES
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|fences| fences.contains(&fence.as_raw()));
+        if let Some(result) = fence_wait_failure(device) {
+            result
+        } else if signaled {
+            vk::Result::SUCCESS
+        } else {
+            vk::Result::NOT_READY
+        }
+    }
+
+    pub unsafe extern "system" fn vkQueueSubmit(
+        queue: vk::Queue,
+        _submit_count: u32,
+        _submits: *const vk::SubmitInfo,
+        fence: vk::Fence,
+    ) -> vk::Result {
+        record_handle(queue, "vkQueueSubmit", fence, ptr::null());
+        let stalled = STALLED_QUEUES
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|queues| queues.contains(&queue.as_raw()));
+        if !stalled && fence != vk::Fence::null() {
+            signal_fence(fence);
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkCreatePipelineCache(
+        device: vk::Device,
+        create_info: *const vk::PipelineCacheCreateInfo,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+        pipeline_cache: *mut vk::PipelineCache,
+    ) -> vk::Result {
+        *pipeline_cache = next_handle();
+        record_handle(
+            device,
+            "vkCreatePipelineCache",
+            *pipeline_cache,
+            allocation_callbacks,
+        );
+        let create_info = &*create_info;
+        let initial_data = match create_info.initial_data_size {
+            0 => Vec::new(),
+            size => {
+                std::slice::from_raw_parts(create_info.p_initial_data.cast::<u8>(), size).to_vec()
+            }
+        };
+        PIPELINE_CACHES
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Default::default)
+            .insert((*pipeline_cache).as_raw(), initial_data);
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkDestroyPipelineCache(
+        device: vk::Device,
+        pipeline_cache: vk::PipelineCache,
+        allocation_callbacks: *const vk::AllocationCallbacks,
+    ) {
+        record_handle(
+            device,
+            "vkDestroyPipelineCache",
+            pipeline_cache,
+            allocation_callbacks,
+        );
+        if let Some(pipeline_caches) = PIPELINE_CACHES.lock().unwrap().as_mut() {
+            pipeline_caches.remove(&pipeline_cache.as_raw());
+        }
+    }
+
+    pub unsafe extern "system" fn vkGetPipelineCacheData(
+        _device: vk::Device,
+        pipeline_cache: vk::PipelineCache,
+        data_size: *mut usize,
+        data: *mut c_void,
+    ) -> vk::Result {
+        let pipeline_caches = PIPELINE_CACHES.lock().unwrap();
+        let cache_data = &pipeline_caches.as_ref().unwrap()[&pipeline_cache.as_raw()];
+        if data.is_null() {
+            *data_size = cache_data.len();
+            return vk::Result::SUCCESS;
+        }
+        let size = cache_data.len().min(*data_size);
+        ptr::copy_nonoverlapping(cache_data.as_ptr(), data.cast(), size);
+        *data_size = size;
+        if size < cache_data.len() {
+            vk::Result::INCOMPLETE
+        } else {
+            vk::Result::SUCCESS
+        }
+    }
+
+    pub unsafe extern "system" fn vkMergePipelineCaches(
+        device: vk::Device,
+        dst_cache: vk::PipelineCache,
+        src_cache_count: u32,
+        src_caches: *const vk::PipelineCache,
+    ) -> vk::Result {
+        let mut pipeline_caches = PIPELINE_CACHES.lock().unwrap();
+        let pipeline_caches = pipeline_caches.as_mut().unwrap();
+        for i in 0..src_cache_count as usize {
+            let src_cache = *src_caches.add(i);
+            record_handle(device, "vkMergePipelineCaches", src_cache, ptr::null());
+            let src_data = pipeline_caches[&src_cache.as_raw()].clone();
+            pipeline_caches
+                .get_mut(&dst_cache.as_raw())
+                .unwrap()
+                .extend(src_data);
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkAllocateCommandBuffers(
+        device: vk::Device,
+        allocate_info: *const vk::CommandBufferAllocateInfo,
+        command_buffers: *mut vk::CommandBuffer,
+    ) -> vk::Result {
+        let allocate_info = &*allocate_info;
+        for i in 0..allocate_info.command_buffer_count as usize {
+            *command_buffers.add(i) = next_handle();
+            record(
+                device,
+                Call::pooled(
+                    "vkAllocateCommandBuffers",
+                    *command_buffers.add(i),
+                    allocate_info.command_pool,
+                ),
+            );
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkBeginCommandBuffer(
+        command_buffer: vk::CommandBuffer,
+        _begin_info: *const vk::CommandBufferBeginInfo,
+    ) -> vk::Result {
+        record_handle(
+            command_buffer,
+            "vkBeginCommandBuffer",
+            command_buffer,
+            ptr::null(),
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkEndCommandBuffer(
+        command_buffer: vk::CommandBuffer,
+    ) -> vk::Result {
+        record_handle(
+            command_buffer,
+            "vkEndCommandBuffer",
+            command_buffer,
+            ptr::null(),
+        );
+        vk::Result::SUCCESS
+    }
+
+    command!(
+        vkCmdBeginRenderPass,
+        *const vk::RenderPassBeginInfo,
+        vk::SubpassContents
+    );
+    command!(vkCmdNextSubpass, vk::SubpassContents);
+    command!(vkCmdEndRenderPass);
+    command!(vkCmdBeginRendering, *const vk::RenderingInfo);
+    command!(vkCmdEndRendering);
+    command!(vkCmdBeginQuery, vk::QueryPool, u32, vk::QueryControlFlags);
+    command!(vkCmdEndQuery, vk::QueryPool, u32);
+    command!(vkCmdBeginDebugUtilsLabelEXT, *const vk::DebugUtilsLabelEXT);
+    command!(vkCmdEndDebugUtilsLabelEXT);
+
+    pub unsafe extern "system" fn vkFreeCommandBuffers(
+        device: vk::Device,
+        command_pool: vk::CommandPool,
+        command_buffer_count: u32,
+        command_buffers: *const vk::CommandBuffer,
+    ) {
+        for i in 0..command_buffer_count as usize {
+            record(
+                device,
+                Call::pooled(
+                    "vkFreeCommandBuffers",
+                    *command_buffers.add(i),
+                    command_pool,
+                ),
+            );
+        }
+    }
+
+    pub unsafe extern "system" fn vkAllocateDescriptorSets(
+        device: vk::Device,
+        allocate_info: *const vk::DescriptorSetAllocateInfo,
+        descriptor_sets: *mut vk::DescriptorSet,
+    ) -> vk::Result {
+        let allocate_info = &*allocate_info;
+        for i in 0..allocate_info.descriptor_set_count as usize {
+            *descriptor_sets.add(i) = next_handle();
+            record(
+                device,
+                Call::pooled(
+                    "vkAllocateDescriptorSets",
+                    *descriptor_sets.add(i),
+                    allocate_info.descriptor_pool,
+                ),
+            );
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkFreeDescriptorSets(
+        device: vk::Device,
+        descriptor_pool: vk::DescriptorPool,
+        descriptor_set_count: u32,
+        descriptor_sets: *const vk::DescriptorSet,
+    ) -> vk::Result {
+        for i in 0..descriptor_set_count as usize {
+            record(
+                device,
+                Call::pooled(
+                    "vkFreeDescriptorSets",
+                    *descriptor_sets.add(i),
+                    descriptor_pool,
+                ),
+            );
+        }
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkSetDebugUtilsObjectNameEXT(
+        device: vk::Device,
+        name_info: *const vk::DebugUtilsObjectNameInfoEXT,
+    ) -> vk::Result {
+        let name_info = &*name_info;
+        record(
+            device,
+            Call {
+                function: "vkSetDebugUtilsObjectNameEXT",
+                handle: name_info.object_handle,
+                pool: None,
+                allocation_callbacks: None,
+                name: Some(
+                    CStr::from_ptr(name_info.p_object_name)
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+            },
+        );
+        vk::Result::SUCCESS
+    }
+
+    pub unsafe extern "system" fn vkGetDeviceProcAddr(
+        _device: vk::Device,
+        name: *const c_char,
+    ) -> vk::PFN_vkVoidFunction {
+        lookup(CStr::from_ptr(name))
+    }
+
+    pub unsafe extern "system" fn vkGetInstanceProcAddr(
+        _instance: vk::Instance,
+        name: *const c_char,
+    ) -> vk::PFN_vkVoidFunction {
+        lookup(CStr::from_ptr(name))
+    }
+}
+
+use stubs::vkGetInstanceProcAddr;
+
+macro_rules! lookup_table {
+    ($name:expr, [$($stub:ident,)*]) => {
+        match $name.to_bytes() {
+            $(
+                name if name == stringify!($stub).as_bytes() => {
+                    Some(std::mem::transmute::<*const (), unsafe extern "system" fn()>(
+                        stubs::$stub as *const (),
+                    ))
+                }
+            )*
+            _ => None,
+        }
+    };
+}
+
+unsafe fn lookup(name: &CStr) -> vk::PFN_vkVoidFunction {
+    lookup_table!(
+        name,
+        [
+            vkGetInstanceProcAddr,
+            vkGetDeviceProcAddr,
+            vkCreateInstance,
+            vkDestroyInstance,
+            vkCreateDevice,
+            vkDestroyDevice,
+            vkDeviceWaitIdle,
+            vkCreateHeadlessSurfaceEXT,
+            vkDestroySurfaceKHR,
+            vkCreateDebugUtilsMessengerEXT,
+            vkDestroyDebugUtilsMessengerEXT,
+            vkSetDebugUtilsObjectNameEXT,
+            vkAllocateMemory,
+            vkFreeMemory,
+            vkMapMemory,
+            vkUnmapMemory,
+            vkFlushMappedMemoryRanges,
+            vkInvalidateMappedMemoryRanges,
+            vkGetBufferMemoryRequirements,
+            vkGetImageMemoryRequirements,
+            vkBindBufferMemory,
+            vkBindImageMemory,
+            vkCreateBuffer,
+            vkDestroyBuffer,
+            vkCreateBufferView,
+            vkDestroyBufferView,
+            vkCreateCommandPool,
+            vkDestroyCommandPool,
+            vkCreateDescriptorPool,
+            vkDestroyDescriptorPool,
+            vkCreateDescriptorSetLayout,
+            vkDestroyDescriptorSetLayout,
+            vkCreateEvent,
+            vkDestroyEvent,
+            vkCreateFence,
+            vkDestroyFence,
+            vkGetFenceStatus,
+            vkWaitForFences,
+            vkQueueSubmit,
+            vkCreateFramebuffer,
+            vkDestroyFramebuffer,
+            vkCreateImage,
+            vkDestroyImage,
+            vkCreateImageView,
+            vkDestroyImageView,
+            vkCreateGraphicsPipelines,
+            vkCreateComputePipelines,
+            vkDestroyPipeline,
+            vkCreatePipelineLayout,
+            vkDestroyPipelineLayout,
+            vkCreatePipelineCache,
+            vkDestroyPipelineCache,
+            vkGetPipelineCacheData,
+            vkMergePipelineCaches,
+            vkCreateQueryPool,
+            vkDestroyQueryPool,
+            vkCreateRenderPass,
+            vkCreateRenderPass2,
+            vkDestroyRenderPass,
+            vkCreateSampler,
+            vkDestroySampler,
+            vkCreateSemaphore,
+            vkDestroySemaphore,
+            vkCreateShaderModule,
+            vkDestroyShaderModule,
+            vkCreateDescriptorUpdateTemplate,
+            vkDestroyDescriptorUpdateTemplate,
+            vkCreateSamplerYcbcrConversion,
+            vkDestroySamplerYcbcrConversion,
+            vkAllocateCommandBuffers,
+            vkFreeCommandBuffers,
+            vkBeginCommandBuffer,
+            vkEndCommandBuffer,
+            vkCmdBeginRenderPass,
+            vkCmdNextSubpass,
+            vkCmdEndRenderPass,
+            vkCmdBeginRendering,
+            vkCmdEndRendering,
+            vkCmdBeginQuery,
+            vkCmdEndQuery,
+            vkCmdBeginDebugUtilsLabelEXT,
+            vkCmdEndDebugUtilsLabelEXT,
+            vkAllocateDescriptorSets,
+            vkFreeDescriptorSets,
+            vkCreateSwapchainKHR,
+            vkDestroySwapchainKHR,
+            vkCreateAccelerationStructureKHR,
+            vkDestroyAccelerationStructureKHR,
+            vkCreateAccelerationStructureNV,
+            vkDestroyAccelerationStructureNV,
+            vkCreateDeferredOperationKHR,
+            vkDestroyDeferredOperationKHR,
+            vkCreateRayTracingPipelinesKHR,
+            vkDeferredOperationJoinKHR,
+            vkGetDeferredOperationResultKHR,
+            vkCreatePrivateDataSlotEXT,
+            vkDestroyPrivateDataSlotEXT,
+            vkCreateCuModuleNVX,
+            vkDestroyCuModuleNVX,
+            vkCreateCuFunctionNVX,
+            vkDestroyCuFunctionNVX,
+            vkCreateIndirectCommandsLayoutNV,
+            vkDestroyIndirectCommandsLayoutNV,
+            vkCreateValidationCacheEXT,
+            vkDestroyValidationCacheEXT,
+            vkCreateVideoSessionKHR,
+            vkDestroyVideoSessionKHR,
+            vkCreateVideoSessionParametersKHR,
+            vkDestroyVideoSessionParametersKHR,
+            vkAcquirePerformanceConfigurationINTEL,
+            vkReleasePerformanceConfigurationINTEL,
+            vkCreateDisplayModeKHR,
+            vkGetDrmDisplayEXT,
+            vkAcquireDrmDisplayEXT,
+            vkReleaseDisplayEXT,
+        ]
+    )
+}
@@ -0,0 +1,396 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ash::{prelude::VkResult, vk};
+
+use crate::GuardedResource;
+
+// Size of VkPipelineCacheHeaderVersionOne, whose fields are written least significant byte first
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+// Distinguishes temporary files of concurrent saves within this process
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// File that pipeline cache data is loaded from and saved to
+///
+/// Pipeline cache data is only valid for the device that produced it (and often only for the
+/// same driver version). Data loaded by [`load`](Self::load) is checked against the pipeline
+/// cache header that the device's [`vk::PhysicalDeviceProperties`] call for, and discarded if
+/// it's stale or from another device. [`save`](Self::save) replaces the file atomically, so a
+/// crash never leaves a truncated cache behind. [`create_guarded_pipeline_cache_from_file`] creates
+/// a guarded pipeline cache from the loaded data:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{DeviceExt, Guarded, PipelineCacheFile};
+///
+/// unsafe fn build_pipelines(
+///     device: &ash::Device,
+///     properties: &vk::PhysicalDeviceProperties,
+/// ) -> VkResult<()> {
+///     let file = PipelineCacheFile::new("pipelines.bin", properties);
+///     // Starts empty if the file can't be read
+///     let pipeline_cache =
+///         device.create_guarded_pipeline_cache_from_file(&file, Default::default(), None)?;
+///
+///     create_pipelines(&pipeline_cache)?;
+///     // The cache is only an optimization, so failing to save is harmless
+///     let _ = file.save(&pipeline_cache);
+///     Ok(())
+/// }
+/// # unsafe fn create_pipelines(_: &Guarded<vk::PipelineCache>) -> VkResult<()> { Ok(()) }
+/// ```
+///
+/// [`create_guarded_pipeline_cache_from_file`]: crate::DeviceExt::create_guarded_pipeline_cache_from_file
+#[derive(Clone, Debug)]
+pub struct PipelineCacheFile {
+    path: PathBuf,
+    vendor_id: u32,
+    device_id: u32,
+    pipeline_cache_uuid: [u8; vk::UUID_SIZE],
+}
+
+impl PipelineCacheFile {
+    /// Creates a [`PipelineCacheFile`] at `path` for the device described by `properties`.
+    pub fn new(path: impl Into<PathBuf>, properties: &vk::PhysicalDeviceProperties) -> Self {
+        Self {
+            path: path.into(),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            pipeline_cache_uuid: properties.pipeline_cache_uuid,
+        }
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if `data` starts with a pipeline cache header for this file's device.
+    ///
+    /// The header has to be [`vk::PipelineCacheHeaderVersion::ONE`], at least as long as
+    /// [`vk::PipelineCacheHeaderVersionOne`] and no longer than `data`, and its vendor ID,
+    /// device ID and pipeline cache UUID have to match.
+    pub fn is_compatible(&self, data: &[u8]) -> bool {
+        let Some(header) = data.get(..HEADER_SIZE) else {
+            return false;
+        };
+        let field = |index: usize| {
+            let bytes = &header[4 * index..4 * (index + 1)];
+            u32::from_le_bytes(bytes.try_into().unwrap())
+        };
+
+        let header_size = field(0) as usize;
+        (HEADER_SIZE..=data.len()).contains(&header_size)
+            && vk::PipelineCacheHeaderVersion::from_raw(field(1) as i32)
+                == vk::PipelineCacheHeaderVersion::ONE
+            && field(2) == self.vendor_id
+            && field(3) == self.device_id
+            && header[16..] == self.pipeline_cache_uuid
+    }
+
+    /// Reads the file, returning its contents if they're
+    /// [compatible](Self::is_compatible) and otherwise no data. A missing file also results in
+    /// no data.
+    ///
+    /// # Errors
+    ///
+    /// Returns any other error from reading the file.
+    pub fn load(&self) -> io::Result<Vec<u8>> {
+        match fs::read(&self.path) {
+            Ok(data) if self.is_compatible(&data) => Ok(data),
+            Ok(_) => Ok(Vec::new()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Replaces the file with the data of `pipeline_cache`, as returned by
+    /// [`get_pipeline_cache_data`](ash::Device::get_pipeline_cache_data).
+    ///
+    /// The data is written to a temporary file next to it (named after the file, the process ID
+    /// and a counter, so that concurrent saves don't collide), which is then renamed over the
+    /// file, so readers see either the old or the new contents. On Unix, the directory is synced
+    /// afterwards so that the rename itself survives a crash.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`get_pipeline_cache_data`](ash::Device::get_pipeline_cache_data).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from getting the data (as [`io::ErrorKind::Other`]), from writing or
+    /// renaming the temporary file, or from syncing the directory. The temporary file is removed
+    /// on failure.
+    pub unsafe fn save<Destroyer>(
+        &self,
+        pipeline_cache: &GuardedResource<'_, vk::PipelineCache, Destroyer>,
+    ) -> io::Result<()>
+    where
+        Destroyer: Deref<Target = ash::Device>,
+    {
+        let data = pipeline_cache
+            .destroyer_ref()
+            .get_pipeline_cache_data(**pipeline_cache)
+            .map_err(io::Error::other)?;
+
+        let temp_path = self.temp_path();
+        let result = write_synced(&temp_path, &data)
+            .and_then(|()| fs::rename(&temp_path, &self.path))
+            .and_then(|()| sync_parent_dir(&self.path));
+        if result.is_err() {
+            // The original error is more useful than one about cleaning up.
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        let id = NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed);
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(format!(".{}.{id}.tmp", process::id()));
+        PathBuf::from(temp_path)
+    }
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+// Other platforms either can't open directories or don't need this.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl<'alloc_cb, Destroyer> GuardedResource<'alloc_cb, vk::PipelineCache, Destroyer>
+where
+    Destroyer: Deref<Target = ash::Device>,
+{
+    /// Merges `src_caches` into this pipeline cache. Same as
+    /// [`merge_pipeline_caches`](ash::Device::merge_pipeline_caches), e.g. for combining caches
+    /// that worker threads created pipelines with:
+    ///
+    /// ```
+    /// # use ash::{prelude::VkResult, vk};
+    /// use ashpan::Guarded;
+    ///
+    /// unsafe fn combine(
+    ///     pipeline_cache: &Guarded<vk::PipelineCache>,
+    ///     worker_caches: Vec<Guarded<vk::PipelineCache>>,
+    /// ) -> VkResult<()> {
+    ///     let src_caches: Vec<_> = worker_caches.iter().map(|cache| **cache).collect();
+    ///     pipeline_cache.merge(&src_caches)
+    /// }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// Same as [`merge_pipeline_caches`](ash::Device::merge_pipeline_caches).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`merge_pipeline_caches`](ash::Device::merge_pipeline_caches).
+    pub unsafe fn merge(&self, src_caches: &[vk::PipelineCache]) -> VkResult<()> {
+        self.destroyer_ref()
+            .merge_pipeline_caches(**self, src_caches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use ash::vk;
+
+    use super::HEADER_SIZE;
+    use crate::{mock, DeviceExt, PipelineCacheFile};
+
+    fn properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x2204,
+            pipeline_cache_uuid: [7; vk::UUID_SIZE],
+            ..Default::default()
+        }
+    }
+
+    fn cache_data(properties: &vk::PhysicalDeviceProperties, payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend((HEADER_SIZE as u32).to_le_bytes());
+        data.extend(vk::PipelineCacheHeaderVersion::ONE.as_raw().to_le_bytes());
+        data.extend(properties.vendor_id.to_le_bytes());
+        data.extend(properties.device_id.to_le_bytes());
+        data.extend(properties.pipeline_cache_uuid);
+        data.extend(payload);
+        data
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("ashpan-{}-{name}", process::id()))
+    }
+
+    #[test]
+    fn stale_and_foreign_data_is_incompatible() {
+        let properties = properties();
+        let file = PipelineCacheFile::new("unused", &properties);
+        let data = cache_data(&properties, b"pipelines");
+        assert!(file.is_compatible(&data));
+        assert!(file.is_compatible(&data[..HEADER_SIZE]));
+
+        let mut foreign = properties;
+        foreign.vendor_id += 1;
+        assert!(!file.is_compatible(&cache_data(&foreign, b"pipelines")));
+        let mut foreign = properties;
+        foreign.device_id += 1;
+        assert!(!file.is_compatible(&cache_data(&foreign, b"pipelines")));
+        let mut stale = properties;
+        stale.pipeline_cache_uuid[15] += 1;
+        assert!(!file.is_compatible(&cache_data(&stale, b"pipelines")));
+
+        let mut wrong_version = data.clone();
+        wrong_version[4] = 2;
+        assert!(!file.is_compatible(&wrong_version));
+        let mut short_header = data.clone();
+        short_header[0] = HEADER_SIZE as u8 - 1;
+        assert!(!file.is_compatible(&short_header));
+        let mut long_header = data.clone();
+        long_header[0] = data.len() as u8 + 1;
+        assert!(!file.is_compatible(&long_header));
+        assert!(!file.is_compatible(&data[..HEADER_SIZE - 1]));
+    }
+
+    #[test]
+    fn loading_discards_incompatible_and_missing_files() {
+        let properties = properties();
+        let path = temp_path("loading_discards_incompatible_and_missing_files");
+        let file = PipelineCacheFile::new(&path, &properties);
+
+        let _ = fs::remove_file(&path);
+        assert_eq!(file.load().unwrap(), []);
+
+        let mut stale = properties;
+        stale.pipeline_cache_uuid[0] = 0;
+        fs::write(&path, cache_data(&stale, b"pipelines")).unwrap();
+        assert_eq!(file.load().unwrap(), []);
+
+        let data = cache_data(&properties, b"pipelines");
+        fs::write(&path, &data).unwrap();
+        assert_eq!(file.load().unwrap(), data);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saved_data_round_trips_and_replaces_the_file() {
+        let (_instance, device) = mock::instance_and_device();
+        let properties = properties();
+        let path = temp_path("saved_data_round_trips_and_replaces_the_file");
+        let file = PipelineCacheFile::new(&path, &properties);
+        fs::write(&path, b"old contents").unwrap();
+
+        let data = cache_data(&properties, b"pipelines");
+        let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(&data);
+        let pipeline_cache =
+            unsafe { (&device).create_guarded_pipeline_cache(&create_info, None) }.unwrap();
+        unsafe { file.save(&pipeline_cache) }.unwrap();
+
+        assert_eq!(file.load().unwrap(), data);
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let leftovers: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with(file_name) && name != file_name)
+            .collect();
+        assert!(
+            leftovers.is_empty(),
+            "leftover temporary files: {leftovers:?}"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves_use_distinct_temporary_files() {
+        let file = PipelineCacheFile::new("pipelines.bin", &properties());
+        assert_ne!(file.temp_path(), file.temp_path());
+        assert_eq!(file.temp_path().parent(), file.path().parent());
+    }
+
+    #[test]
+    fn pipeline_caches_are_created_from_saved_files() {
+        let (_instance, device) = mock::instance_and_device();
+        let properties = properties();
+        let path = temp_path("pipeline_caches_are_created_from_saved_files");
+        let file = PipelineCacheFile::new(&path, &properties);
+
+        let _ = fs::remove_file(&path);
+        let pipeline_cache = unsafe {
+            (&device).create_guarded_pipeline_cache_from_file(&file, Default::default(), None)
+        }
+        .unwrap();
+        assert_eq!(
+            unsafe { device.get_pipeline_cache_data(*pipeline_cache) }.unwrap(),
+            []
+        );
+
+        let data = cache_data(&properties, b"pipelines");
+        fs::write(&path, &data).unwrap();
+        let pipeline_cache = unsafe {
+            (&device).create_guarded_pipeline_cache_from_file(&file, Default::default(), None)
+        }
+        .unwrap();
+        assert_eq!(
+            unsafe { device.get_pipeline_cache_data(*pipeline_cache) }.unwrap(),
+            data
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn guarded_caches_are_merged_before_being_destroyed() {
+        let (_instance, device) = mock::instance_and_device();
+        let properties = properties();
+        let data = cache_data(&properties, b"first");
+        let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(&data);
+        let pipeline_cache =
+            unsafe { (&device).create_guarded_pipeline_cache(&create_info, None) }.unwrap();
+
+        let src_cache = {
+            let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(b"second");
+            unsafe { (&device).create_guarded_pipeline_cache(&create_info, None) }.unwrap()
+        };
+        unsafe { pipeline_cache.merge(&[*src_cache]) }.unwrap();
+        let src_handle = *src_cache;
+        drop(src_cache);
+
+        assert_eq!(
+            unsafe { device.get_pipeline_cache_data(*pipeline_cache) }.unwrap(),
+            cache_data(&properties, b"firstsecond")
+        );
+        let calls = mock::calls(&device);
+        assert_eq!(
+            calls[calls.len() - 2..],
+            [
+                mock::Call::new("vkMergePipelineCaches", src_handle, None),
+                mock::Call::new("vkDestroyPipelineCache", src_handle, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::Deref;
+
+use ash::vk;
+
+/// [`vk::CommandBuffer`]s along with the [`vk::CommandPool`] they were allocated from
+///
+/// Command buffers can't be destroyed individually; they have to be freed back to the pool they
+/// were allocated from. [`PooledCommandBuffers`] keeps track of that pool, so that a guard holding
+/// it (with an [`ash::Device`] destroyer) frees all of its command buffers in a single call when
+/// dropped:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{Guarded, PooledCommandBuffers};
+///
+/// unsafe fn record_upload(
+///     device: &ash::Device,
+///     command_pool: vk::CommandPool,
+///     command_buffer: vk::CommandBuffer,
+/// ) -> VkResult<()> {
+///     let command_buffers = PooledCommandBuffers::new(command_pool, vec![command_buffer]);
+///     let command_buffers = Guarded::new(command_buffers, device, None);
+///     // If recording fails, command_buffer is freed back to command_pool
+///     record(command_buffers[0])?;
+///     Ok(())
+/// }
+/// # unsafe fn record(_: vk::CommandBuffer) -> VkResult<()> { unimplemented!() }
+/// ```
+///
+/// Usually it's more convenient to use
+/// [`DeviceExt::allocate_guarded_command_buffers`](crate::DeviceExt::allocate_guarded_command_buffers).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PooledCommandBuffers {
+    command_pool: vk::CommandPool,
+    command_buffers: Vec<vk::CommandBuffer>,
+}
+
+impl PooledCommandBuffers {
+    /// Pairs `command_buffers` with the `command_pool` they were allocated from.
+    pub fn new(command_pool: vk::CommandPool, command_buffers: Vec<vk::CommandBuffer>) -> Self {
+        Self {
+            command_pool,
+            command_buffers,
+        }
+    }
+
+    /// Returns the pool that the command buffers are freed to.
+    pub fn command_pool(&self) -> vk::CommandPool {
+        self.command_pool
+    }
+
+    /// Returns the command buffers without freeing them.
+    pub fn into_command_buffers(self) -> Vec<vk::CommandBuffer> {
+        self.command_buffers
+    }
+}
+
+impl Deref for PooledCommandBuffers {
+    type Target = [vk::CommandBuffer];
+
+    fn deref(&self) -> &[vk::CommandBuffer] {
+        &self.command_buffers
+    }
+}
+
+/// [`vk::DescriptorSet`]s along with the [`vk::DescriptorPool`] they were allocated from
+///
+/// Like [`PooledCommandBuffers`], but for descriptor sets. Freeing individual descriptor sets is
+/// only valid if the pool was created with
+/// [`FREE_DESCRIPTOR_SET`](vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET), so
+/// [`new`](Self::new) takes the pool's create flags and refuses pools without it.
+///
+/// Usually it's more convenient to use
+/// [`DeviceExt::allocate_guarded_descriptor_sets`](crate::DeviceExt::allocate_guarded_descriptor_sets).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PooledDescriptorSets {
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+}
+
+impl PooledDescriptorSets {
+    /// Pairs `descriptor_sets` with the `descriptor_pool` they were allocated from, which was
+    /// created with `pool_flags`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pool_flags` doesn't contain
+    /// [`FREE_DESCRIPTOR_SET`](vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET).
+    pub fn new(
+        descriptor_pool: vk::DescriptorPool,
+        pool_flags: vk::DescriptorPoolCreateFlags,
+        descriptor_sets: Vec<vk::DescriptorSet>,
+    ) -> Self {
+        assert!(
+            pool_flags.contains(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET),
+            "descriptor sets can only be freed to pools created with FREE_DESCRIPTOR_SET"
+        );
+        Self {
+            descriptor_pool,
+            descriptor_sets,
+        }
+    }
+
+    /// Returns the pool that the descriptor sets are freed to.
+    pub fn descriptor_pool(&self) -> vk::DescriptorPool {
+        self.descriptor_pool
+    }
+
+    /// Returns the descriptor sets without freeing them.
+    pub fn into_descriptor_sets(self) -> Vec<vk::DescriptorSet> {
+        self.descriptor_sets
+    }
+}
+
+impl Deref for PooledDescriptorSets {
+    type Target = [vk::DescriptorSet];
+
+    fn deref(&self) -> &[vk::DescriptorSet] {
+        &self.descriptor_sets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::{self, Handle};
+
+    use crate::PooledDescriptorSets;
+
+    #[test]
+    #[should_panic(expected = "FREE_DESCRIPTOR_SET")]
+    fn descriptor_sets_are_refused_for_pools_without_free_descriptor_set() {
+        PooledDescriptorSets::new(
+            vk::DescriptorPool::from_raw(1),
+            vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND,
+            vec![vk::DescriptorSet::from_raw(2)],
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::ffi::{c_void, CStr};
+use std::fmt;
+use std::mem;
+
+use ash::vk;
+
+/// Raw Vulkan function table generated by [`ash`], such as [`vk::NvxBinaryImportFn`]
+///
+/// Implemented for the tables of extensions that [`ash::extensions`] doesn't provide a loader
+/// for, but whose objects still need destroying.
+pub trait FnTable: Clone {
+    /// Loads the table's function pointers via `load`.
+    fn load_with(load: impl FnMut(&CStr) -> *const c_void) -> Self;
+}
+
+macro_rules! fn_table {
+    ($($Fp:ty,)*) => {
+        $(
+            impl FnTable for $Fp {
+                fn load_with(load: impl FnMut(&CStr) -> *const c_void) -> Self {
+                    Self::load(load)
+                }
+            }
+        )*
+    };
+}
+
+fn_table!(
+    vk::ExtDirectModeDisplayFn,
+    vk::ExtValidationCacheFn,
+    vk::IntelPerformanceQueryFn,
+    vk::KhrVideoQueueFn,
+    vk::NvDeviceGeneratedCommandsFn,
+    vk::NvxBinaryImportFn,
+);
+
+/// Destroyer made of a raw device-level function table
+///
+/// Some extensions have no loader in [`ash::extensions`], so their objects can't be destroyed
+/// by one. [`DeviceFn`] fills that role: it loads a raw [`FnTable`] via
+/// [`get_device_proc_addr`](ash::Instance::get_device_proc_addr) and remembers which device to
+/// call it with.
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{DeviceFn, Guarded, ValidationCacheExt};
+///
+/// unsafe fn create_validation_cache(
+///     validation_cache_fn: &DeviceFn<vk::ExtValidationCacheFn>,
+/// ) -> VkResult<Guarded<vk::ValidationCacheEXT>> {
+///     let create_info = unimplemented!();
+///     validation_cache_fn.create_guarded_validation_cache(create_info, None)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DeviceFn<Fp> {
+    device: vk::Device,
+    fp: Fp,
+}
+
+impl<Fp: FnTable> DeviceFn<Fp> {
+    /// Loads `Fp` for `device`.
+    pub fn new(instance: &ash::Instance, device: &ash::Device) -> Self {
+        let handle = device.handle();
+        let fp = Fp::load_with(|name| unsafe {
+            mem::transmute(instance.get_device_proc_addr(handle, name.as_ptr()))
+        });
+        Self { device: handle, fp }
+    }
+}
+
+impl<Fp> DeviceFn<Fp> {
+    /// Returns the device that functions are called with.
+    pub fn device(&self) -> vk::Device {
+        self.device
+    }
+
+    /// Returns the raw function table.
+    pub fn fp(&self) -> &Fp {
+        &self.fp
+    }
+}
+
+impl<Fp> fmt::Debug for DeviceFn<Fp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceFn")
+            .field("device", &self.device)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Destroyer made of a raw instance-level function table and a physical device
+///
+/// Like [`DeviceFn`], but for extensions whose objects belong to a [`vk::PhysicalDevice`], such
+/// as [`vk::DisplayKHR`]s that are released via [`vk::ExtDirectModeDisplayFn`].
+#[derive(Clone)]
+pub struct PhysicalDeviceFn<Fp> {
+    physical_device: vk::PhysicalDevice,
+    fp: Fp,
+}
+
+impl<Fp: FnTable> PhysicalDeviceFn<Fp> {
+    /// Loads `Fp` for `physical_device`.
+    pub fn new(
+        entry: &ash::Entry,
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> Self {
+        let handle = instance.handle();
+        let fp = Fp::load_with(|name| unsafe {
+            mem::transmute(entry.get_instance_proc_addr(handle, name.as_ptr()))
+        });
+        Self {
+            physical_device,
+            fp,
+        }
+    }
+}
+
+impl<Fp> PhysicalDeviceFn<Fp> {
+    /// Returns the physical device that functions are called with.
+    pub fn physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+
+    /// Returns the raw function table.
+    pub fn fp(&self) -> &Fp {
+        &self.fp
+    }
+}
+
+impl<Fp> fmt::Debug for PhysicalDeviceFn<Fp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhysicalDeviceFn")
+            .field("physical_device", &self.physical_device)
+            .finish_non_exhaustive()
+    }
+}
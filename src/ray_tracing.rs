@@ -0,0 +1,440 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use ash::{extensions::khr, vk, RawPtr};
+
+use crate::{device::PipelinesResult, Destroyable, GuardedResource};
+
+/// Ray tracing pipelines along with the deferred operation that creates them
+///
+/// Created by [`RayTracingPipelineExt::create_guarded_deferred_ray_tracing_pipelines`]. The
+/// implementation keeps reading the create infos and writing the pipeline handles until the
+/// deferred operation completes, so both the create infos and the deferred operation stay borrowed
+/// for `'c`, and [`pipelines`](Self::pipelines) is unsafe. When destroyed, the deferred operation
+/// is joined until it completes, then the pipelines are destroyed. Dropping it without destroying
+/// it (e.g. after [`GuardedResource::take`]) still joins the deferred operation first.
+///
+/// [`visit_handles`](Destroyable::visit_handles) only reports the pipelines once the deferred
+/// operation is known to have completed, i.e. if it wasn't deferred or after
+/// [`join`](Self::join). As a result, the `leak-tracking` feature doesn't register the pipelines
+/// of an operation that was still pending when they were guarded.
+pub struct DeferredRayTracingPipelines<'c> {
+    deferred_host_operations: khr::DeferredHostOperations,
+    deferred_operation: vk::DeferredOperationKHR,
+    // Boxed so that the address passed to Vulkan stays valid while the operation is pending.
+    pipelines: Box<[vk::Pipeline]>,
+    completed: AtomicBool,
+    borrows: PhantomData<&'c [vk::RayTracingPipelineCreateInfoKHR]>,
+}
+
+impl DeferredRayTracingPipelines<'_> {
+    /// Returns the deferred operation, e.g. for joining it from worker threads.
+    pub fn deferred_operation(&self) -> vk::DeferredOperationKHR {
+        self.deferred_operation
+    }
+
+    /// Returns the pipelines. Pipelines that failed to be created are null.
+    ///
+    /// # Safety
+    ///
+    /// The deferred operation must have completed, e.g. via [`join`](Self::join).
+    pub unsafe fn pipelines(&self) -> &[vk::Pipeline] {
+        &self.pipelines
+    }
+
+    /// Joins the deferred operation until it completes (if it hasn't already), then returns its
+    /// result.
+    pub fn join(&self) -> vk::Result {
+        // The deferred operation is borrowed from its guard for as long as `self` lives.
+        let result = unsafe { self.join_unchecked() };
+        self.completed.store(true, Ordering::Release);
+        result
+    }
+
+    unsafe fn join_unchecked(&self) -> vk::Result {
+        let fp = self.deferred_host_operations.fp();
+        let device = self.deferred_host_operations.device();
+        let operation = self.deferred_operation;
+
+        loop {
+            match (fp.get_deferred_operation_result_khr)(device, operation) {
+                vk::Result::NOT_READY => {}
+                result => return result,
+            }
+            // THREAD_IDLE_KHR and THREAD_DONE_KHR mean other threads are still working on the
+            // operation, and errors (e.g. running out of host memory) only mean that this join
+            // failed, so keep joining until the result is ready.
+            if (fp.deferred_operation_join_khr)(device, operation) != vk::Result::SUCCESS {
+                thread::yield_now();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for DeferredRayTracingPipelines<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeferredRayTracingPipelines")
+            .field("deferred_operation", &self.deferred_operation)
+            .field("pipelines", &self.pipelines)
+            .field("completed", &self.completed)
+            .finish()
+    }
+}
+
+impl Destroyable for DeferredRayTracingPipelines<'_> {
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        // Failures are irrelevant since the pipelines are about to be destroyed anyway.
+        let _ = self.join();
+        for pipeline in self.pipelines.iter_mut() {
+            pipeline.destroy_with(device, allocation_callbacks);
+        }
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        if self.completed.load(Ordering::Acquire) {
+            for pipeline in self.pipelines.iter() {
+                pipeline.visit_handles(visitor);
+            }
+        }
+    }
+}
+
+impl Drop for DeferredRayTracingPipelines<'_> {
+    fn drop(&mut self) {
+        // The implementation may still be writing to `pipelines`.
+        if !*self.completed.get_mut() {
+            let _ = self.join();
+        }
+    }
+}
+
+/// Extension trait adding guarded methods to [`khr::RayTracingPipeline`]
+#[allow(clippy::missing_safety_doc)]
+pub trait RayTracingPipelineExt: Sized + Deref<Target = khr::RayTracingPipeline> {
+    /// Same as
+    /// [`create_ray_tracing_pipelines`](khr::RayTracingPipeline::create_ray_tracing_pipelines)
+    /// without a deferred operation, but returns guarded [`vk::Pipeline`]s that are destroyed
+    /// by `device`.
+    ///
+    /// Unlike [`ash`], pipelines that were successfully created are also returned on failure
+    /// (e.g. [`PIPELINE_COMPILE_REQUIRED`](vk::Result::PIPELINE_COMPILE_REQUIRED)).
+    unsafe fn create_guarded_ray_tracing_pipelines<'a, DeviceRef>(
+        &self,
+        device: DeviceRef,
+        pipeline_cache: vk::PipelineCache,
+        create_infos: &[vk::RayTracingPipelineCreateInfoKHR],
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> PipelinesResult<GuardedResource<'a, Vec<vk::Pipeline>, DeviceRef>>
+    where
+        DeviceRef: Deref<Target = ash::Device>;
+
+    /// Same as
+    /// [`create_ray_tracing_pipelines`](khr::RayTracingPipeline::create_ray_tracing_pipelines)
+    /// with `deferred_operation`, but returns guarded [`DeferredRayTracingPipelines`] whose
+    /// pipelines are destroyed by `device`.
+    ///
+    /// Returns `Ok` for [`SUCCESS`](vk::Result::SUCCESS),
+    /// [`OPERATION_DEFERRED_KHR`](vk::Result::OPERATION_DEFERRED_KHR) and
+    /// [`OPERATION_NOT_DEFERRED_KHR`](vk::Result::OPERATION_NOT_DEFERRED_KHR), and `Err` along
+    /// with any pipelines that were created otherwise.
+    ///
+    /// # Safety
+    ///
+    /// Same as
+    /// [`create_ray_tracing_pipelines`](khr::RayTracingPipeline::create_ray_tracing_pipelines).
+    /// In addition, everything that `create_infos` point to (stages, groups, libraries, etc.)
+    /// must stay valid until the deferred operation completes. `create_infos` themselves and
+    /// `deferred_operation` are borrowed by the returned guard, which joins the operation before
+    /// it's dropped.
+    unsafe fn create_guarded_deferred_ray_tracing_pipelines<'a, 'c, DeviceRef, OperationRef>(
+        &self,
+        device: DeviceRef,
+        deferred_operation: &'c mut GuardedResource<'_, vk::DeferredOperationKHR, OperationRef>,
+        pipeline_cache: vk::PipelineCache,
+        create_infos: &'c [vk::RayTracingPipelineCreateInfoKHR],
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> PipelinesResult<GuardedResource<'a, DeferredRayTracingPipelines<'c>, DeviceRef>>
+    where
+        DeviceRef: Deref<Target = ash::Device>,
+        OperationRef: Deref<Target = khr::DeferredHostOperations>;
+}
+
+impl<LoaderRef> RayTracingPipelineExt for LoaderRef
+where
+    LoaderRef: Clone + Deref<Target = khr::RayTracingPipeline>,
+{
+    unsafe fn create_guarded_ray_tracing_pipelines<'a, DeviceRef>(
+        &self,
+        device: DeviceRef,
+        pipeline_cache: vk::PipelineCache,
+        create_infos: &[vk::RayTracingPipelineCreateInfoKHR],
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> PipelinesResult<GuardedResource<'a, Vec<vk::Pipeline>, DeviceRef>>
+    where
+        DeviceRef: Deref<Target = ash::Device>,
+    {
+        let mut pipelines = vec![vk::Pipeline::null(); create_infos.len()];
+        let result = (self.fp().create_ray_tracing_pipelines_khr)(
+            self.device(),
+            vk::DeferredOperationKHR::null(),
+            pipeline_cache,
+            create_infos.len() as u32,
+            create_infos.as_ptr(),
+            allocation_callbacks.as_raw_ptr(),
+            pipelines.as_mut_ptr(),
+        );
+
+        let pipelines = GuardedResource::new(pipelines, device, allocation_callbacks);
+        match result {
+            vk::Result::SUCCESS => Ok(pipelines),
+            err => Err((pipelines, err)),
+        }
+    }
+
+    unsafe fn create_guarded_deferred_ray_tracing_pipelines<'a, 'c, DeviceRef, OperationRef>(
+        &self,
+        device: DeviceRef,
+        deferred_operation: &'c mut GuardedResource<'_, vk::DeferredOperationKHR, OperationRef>,
+        pipeline_cache: vk::PipelineCache,
+        create_infos: &'c [vk::RayTracingPipelineCreateInfoKHR],
+        allocation_callbacks: Option<&'a vk::AllocationCallbacks>,
+    ) -> PipelinesResult<GuardedResource<'a, DeferredRayTracingPipelines<'c>, DeviceRef>>
+    where
+        DeviceRef: Deref<Target = ash::Device>,
+        OperationRef: Deref<Target = khr::DeferredHostOperations>,
+    {
+        let mut pipelines = DeferredRayTracingPipelines {
+            deferred_host_operations: khr::DeferredHostOperations::clone(
+                deferred_operation.destroyer_ref(),
+            ),
+            deferred_operation: **deferred_operation,
+            pipelines: vec![vk::Pipeline::null(); create_infos.len()].into_boxed_slice(),
+            completed: AtomicBool::new(false),
+            borrows: PhantomData,
+        };
+        let result = (self.fp().create_ray_tracing_pipelines_khr)(
+            self.device(),
+            pipelines.deferred_operation,
+            pipeline_cache,
+            create_infos.len() as u32,
+            create_infos.as_ptr(),
+            allocation_callbacks.as_raw_ptr(),
+            pipelines.pipelines.as_mut_ptr(),
+        );
+        // Unless the operation was deferred, the pipelines have already been written.
+        *pipelines.completed.get_mut() = result != vk::Result::OPERATION_DEFERRED_KHR;
+
+        let pipelines = GuardedResource::new(pipelines, device, allocation_callbacks);
+        match result {
+            vk::Result::SUCCESS
+            | vk::Result::OPERATION_DEFERRED_KHR
+            | vk::Result::OPERATION_NOT_DEFERRED_KHR => Ok(pipelines),
+            err => Err((pipelines, err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::{
+        extensions::khr,
+        vk::{self, Handle},
+    };
+
+    use crate::{mock, DeferredHostOperationsExt, Destroyable, RayTracingPipelineExt};
+
+    #[test]
+    fn partial_results_are_guarded() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::RayTracingPipeline::new(&instance, &device);
+        let create_infos = [
+            vk::RayTracingPipelineCreateInfoKHR::default(),
+            vk::RayTracingPipelineCreateInfoKHR {
+                flags: vk::PipelineCreateFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED,
+                ..Default::default()
+            },
+        ];
+
+        let Err((pipelines, err)) = (unsafe {
+            (&loader).create_guarded_ray_tracing_pipelines(
+                &device,
+                vk::PipelineCache::null(),
+                &create_infos,
+                None,
+            )
+        }) else {
+            panic!("creation should partially fail");
+        };
+        assert_eq!(err, vk::Result::PIPELINE_COMPILE_REQUIRED);
+        assert_eq!(pipelines[1], vk::Pipeline::null());
+        let handle = pipelines[0];
+        drop(pipelines);
+
+        assert_eq!(
+            mock::calls(&device)[1..],
+            [
+                mock::Call::new("vkCreateRayTracingPipelinesKHR", handle, None),
+                mock::Call::new("vkDestroyPipeline", handle, None),
+                mock::Call::new("vkDestroyPipeline", vk::Pipeline::null(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn deferred_operation_is_joined_before_destruction() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::RayTracingPipeline::new(&instance, &device);
+        let deferred_host_operations = khr::DeferredHostOperations::new(&instance, &device);
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let callbacks = Some(&allocation_callbacks);
+
+        let create_infos = [vk::RayTracingPipelineCreateInfoKHR::default()];
+        let mut deferred_operation =
+            unsafe { (&deferred_host_operations).create_guarded_deferred_operation(None) }.unwrap();
+        let pipelines = unsafe {
+            (&loader).create_guarded_deferred_ray_tracing_pipelines(
+                &device,
+                &mut deferred_operation,
+                vk::PipelineCache::null(),
+                &create_infos,
+                callbacks,
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap();
+        let mut handles = vec![];
+        pipelines.visit_handles(&mut |_, handle| handles.push(handle));
+        assert!(handles.is_empty(), "pending pipelines shouldn't be visited");
+        drop(pipelines);
+        let handle = *deferred_operation;
+        drop(deferred_operation);
+
+        let calls = mock::calls(&device);
+        let pipeline = vk::Pipeline::from_raw(calls[2].handle);
+        assert_eq!(
+            calls[1..],
+            [
+                mock::Call::new("vkCreateDeferredOperationKHR", handle, None),
+                mock::Call::new("vkCreateRayTracingPipelinesKHR", pipeline, callbacks),
+                mock::Call::new("vkDeferredOperationJoinKHR", handle, None),
+                mock::Call::new("vkDestroyPipeline", pipeline, callbacks),
+                mock::Call::new("vkDestroyDeferredOperationKHR", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipelines_are_available_after_join() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::RayTracingPipeline::new(&instance, &device);
+        let deferred_host_operations = khr::DeferredHostOperations::new(&instance, &device);
+        let create_infos = [vk::RayTracingPipelineCreateInfoKHR::default(); 2];
+        let mut deferred_operation =
+            unsafe { (&deferred_host_operations).create_guarded_deferred_operation(None) }.unwrap();
+
+        let pipelines = unsafe {
+            (&loader).create_guarded_deferred_ray_tracing_pipelines(
+                &device,
+                &mut deferred_operation,
+                vk::PipelineCache::null(),
+                &create_infos,
+                None,
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap();
+
+        assert_eq!(pipelines.join(), vk::Result::SUCCESS);
+        let pipeline_handles = unsafe { pipelines.pipelines() };
+        assert_eq!(pipeline_handles.len(), 2);
+        assert!(pipeline_handles
+            .iter()
+            .all(|pipeline| *pipeline != vk::Pipeline::null()));
+
+        let mut handles = vec![];
+        pipelines.visit_handles(&mut |_, handle| handles.push(handle));
+        let raw_handles: Vec<_> = pipeline_handles.iter().map(|p| p.as_raw()).collect();
+        assert_eq!(handles, raw_handles);
+    }
+
+    #[test]
+    fn failed_joins_are_retried() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::RayTracingPipeline::new(&instance, &device);
+        let deferred_host_operations = khr::DeferredHostOperations::new(&instance, &device);
+        let create_infos = [vk::RayTracingPipelineCreateInfoKHR::default()];
+        let mut deferred_operation =
+            unsafe { (&deferred_host_operations).create_guarded_deferred_operation(None) }.unwrap();
+        mock::fail_next_join(*deferred_operation, vk::Result::ERROR_OUT_OF_HOST_MEMORY);
+
+        let pipelines = unsafe {
+            (&loader).create_guarded_deferred_ray_tracing_pipelines(
+                &device,
+                &mut deferred_operation,
+                vk::PipelineCache::null(),
+                &create_infos,
+                None,
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap();
+        assert_eq!(pipelines.join(), vk::Result::SUCCESS);
+
+        let functions: Vec<_> = mock::calls(&device)[2..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "vkCreateRayTracingPipelinesKHR",
+                "vkDeferredOperationJoinKHR",
+                "vkDeferredOperationJoinKHR"
+            ]
+        );
+    }
+
+    #[test]
+    fn taken_pipelines_are_joined_when_dropped() {
+        let (instance, device) = mock::instance_and_device();
+        let loader = khr::RayTracingPipeline::new(&instance, &device);
+        let deferred_host_operations = khr::DeferredHostOperations::new(&instance, &device);
+        let create_infos = [vk::RayTracingPipelineCreateInfoKHR::default()];
+        let mut deferred_operation =
+            unsafe { (&deferred_host_operations).create_guarded_deferred_operation(None) }.unwrap();
+
+        let pipelines = unsafe {
+            (&loader).create_guarded_deferred_ray_tracing_pipelines(
+                &device,
+                &mut deferred_operation,
+                vk::PipelineCache::null(),
+                &create_infos,
+                None,
+            )
+        }
+        .map_err(|(_, err)| err)
+        .unwrap()
+        .take();
+        drop(pipelines);
+
+        let functions: Vec<_> = mock::calls(&device)[2..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "vkCreateRayTracingPipelinesKHR",
+                "vkDeferredOperationJoinKHR"
+            ]
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use ash::{extensions::ext, prelude::VkResult, vk};
+
+use crate::{GuardedResource, PooledCommandBuffers};
+
+/// Something that commands can be recorded into, i.e. a [`Recording`] or one of the scopes
+/// nested in it
+///
+/// Each `begin_*` method emits a begin command and returns a scope that emits the matching end
+/// command when dropped. Scopes mutably borrow the scope they're nested in, so they're always
+/// ended innermost first.
+pub trait CommandScope {
+    /// Returns the device that the command buffer belongs to.
+    fn device(&self) -> &ash::Device;
+
+    /// Returns the command buffer being recorded.
+    fn command_buffer(&self) -> vk::CommandBuffer;
+
+    /// Begins a render pass that ends when the returned scope is dropped. Same as
+    /// [`cmd_begin_render_pass`](ash::Device::cmd_begin_render_pass).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`cmd_begin_render_pass`](ash::Device::cmd_begin_render_pass).
+    unsafe fn begin_render_pass(
+        &mut self,
+        begin_info: &vk::RenderPassBeginInfo,
+        contents: vk::SubpassContents,
+    ) -> RenderPassScope<'_>
+    where
+        Self: Sized,
+    {
+        let this: &Self = self;
+        this.device()
+            .cmd_begin_render_pass(this.command_buffer(), begin_info, contents);
+        RenderPassScope {
+            device: this.device(),
+            command_buffer: this.command_buffer(),
+            _scope: PhantomData,
+        }
+    }
+
+    /// Begins dynamic rendering that ends when the returned scope is dropped. Same as
+    /// [`cmd_begin_rendering`](ash::Device::cmd_begin_rendering).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`cmd_begin_rendering`](ash::Device::cmd_begin_rendering).
+    unsafe fn begin_rendering(&mut self, rendering_info: &vk::RenderingInfo) -> RenderingScope<'_>
+    where
+        Self: Sized,
+    {
+        let this: &Self = self;
+        this.device()
+            .cmd_begin_rendering(this.command_buffer(), rendering_info);
+        RenderingScope {
+            device: this.device(),
+            command_buffer: this.command_buffer(),
+            _scope: PhantomData,
+        }
+    }
+
+    /// Begins a query that ends when the returned scope is dropped. Same as
+    /// [`cmd_begin_query`](ash::Device::cmd_begin_query).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`cmd_begin_query`](ash::Device::cmd_begin_query).
+    unsafe fn begin_query(
+        &mut self,
+        query_pool: vk::QueryPool,
+        query: u32,
+        flags: vk::QueryControlFlags,
+    ) -> QueryScope<'_>
+    where
+        Self: Sized,
+    {
+        let this: &Self = self;
+        this.device()
+            .cmd_begin_query(this.command_buffer(), query_pool, query, flags);
+        QueryScope {
+            device: this.device(),
+            command_buffer: this.command_buffer(),
+            query_pool,
+            query,
+            _scope: PhantomData,
+        }
+    }
+
+    /// Begins a debug label region that ends when the returned scope is dropped. Same as
+    /// [`cmd_begin_debug_utils_label`](ext::DebugUtils::cmd_begin_debug_utils_label).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`cmd_begin_debug_utils_label`](ext::DebugUtils::cmd_begin_debug_utils_label).
+    unsafe fn begin_debug_label<'s>(
+        &'s mut self,
+        debug_utils: &'s ext::DebugUtils,
+        label: &vk::DebugUtilsLabelEXT,
+    ) -> DebugLabelScope<'s>
+    where
+        Self: Sized,
+    {
+        let this: &Self = self;
+        debug_utils.cmd_begin_debug_utils_label(this.command_buffer(), label);
+        DebugLabelScope {
+            device: this.device(),
+            debug_utils,
+            command_buffer: this.command_buffer(),
+            _scope: PhantomData,
+        }
+    }
+}
+
+/// Command buffer that is being recorded
+///
+/// Created by [`Recording::begin`], which calls
+/// [`begin_command_buffer`](ash::Device::begin_command_buffer). [`finish`](Self::finish) calls
+/// [`end_command_buffer`](ash::Device::end_command_buffer) and returns its result; if the
+/// [`Recording`] is dropped instead (e.g. because recording bailed out with `?`), the command
+/// buffer is ended anyway. Scopes for render passes, queries etc. are begun via
+/// [`CommandScope`]:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{CommandScope, Guarded, PooledCommandBuffers, Recording};
+///
+/// unsafe fn record_frame(
+///     command_buffers: &mut Guarded<PooledCommandBuffers>,
+///     render_pass_begin_info: &vk::RenderPassBeginInfo,
+/// ) -> VkResult<()> {
+///     let mut recording = Recording::begin(command_buffers, 0, &Default::default())?;
+///     {
+///         let render_pass =
+///             recording.begin_render_pass(render_pass_begin_info, vk::SubpassContents::INLINE);
+///         // If this fails, the render pass and command buffer are still ended
+///         record_draws(&render_pass)?;
+///     }
+///     recording.finish()
+/// }
+/// # unsafe fn record_draws(_: &impl CommandScope) -> VkResult<()> { unimplemented!() }
+/// ```
+pub struct Recording<'a> {
+    device: &'a ash::Device,
+    command_buffer: vk::CommandBuffer,
+}
+
+impl<'a> Recording<'a> {
+    /// Begins recording `command_buffers[index]`. All of `command_buffers` stay borrowed until
+    /// recording ends, because command buffers allocated from the same pool mustn't be recorded
+    /// concurrently.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`begin_command_buffer`](ash::Device::begin_command_buffer).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`begin_command_buffer`](ash::Device::begin_command_buffer), in
+    /// which case the command buffer isn't ended.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub unsafe fn begin<'g, Destroyer>(
+        command_buffers: &'a mut GuardedResource<'g, PooledCommandBuffers, Destroyer>,
+        index: usize,
+        begin_info: &vk::CommandBufferBeginInfo,
+    ) -> VkResult<Self>
+    where
+        Destroyer: Deref<Target = ash::Device>,
+    {
+        let command_buffers: &'a GuardedResource<'g, PooledCommandBuffers, Destroyer> =
+            command_buffers;
+        Self::from_raw(
+            command_buffers.destroyer_ref(),
+            command_buffers[index],
+            begin_info,
+        )
+    }
+
+    /// Same as [`begin`](Self::begin), but for a command buffer that isn't guarded.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`begin`](Self::begin). Additionally, `command_buffer` must have been allocated
+    /// from `device` and must not be recorded elsewhere until recording ends.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`begin`](Self::begin).
+    pub unsafe fn from_raw(
+        device: &'a ash::Device,
+        command_buffer: vk::CommandBuffer,
+        begin_info: &vk::CommandBufferBeginInfo,
+    ) -> VkResult<Self> {
+        device.begin_command_buffer(command_buffer, begin_info)?;
+        Ok(Self {
+            device,
+            command_buffer,
+        })
+    }
+
+    /// Ends recording. Same as [`end_command_buffer`](ash::Device::end_command_buffer).
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`end_command_buffer`](ash::Device::end_command_buffer).
+    pub fn finish(self) -> VkResult<()> {
+        let result = unsafe { self.device.end_command_buffer(self.command_buffer) };
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl<'a> CommandScope for Recording<'a> {
+    fn device(&self) -> &ash::Device {
+        self.device
+    }
+
+    fn command_buffer(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+}
+
+impl<'a> Drop for Recording<'a> {
+    fn drop(&mut self) {
+        // Recording was abandoned, so its result doesn't matter.
+        let _ = unsafe { self.device.end_command_buffer(self.command_buffer) };
+    }
+}
+
+impl<'a> fmt::Debug for Recording<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recording")
+            .field("device", &self.device.handle())
+            .field("command_buffer", &self.command_buffer)
+            .finish()
+    }
+}
+
+/// Render pass that ends when dropped
+///
+/// Created by [`CommandScope::begin_render_pass`].
+pub struct RenderPassScope<'s> {
+    device: &'s ash::Device,
+    command_buffer: vk::CommandBuffer,
+    _scope: PhantomData<&'s mut ()>,
+}
+
+impl<'s> RenderPassScope<'s> {
+    /// Advances to the next subpass. Same as
+    /// [`cmd_next_subpass`](ash::Device::cmd_next_subpass).
+    ///
+    /// # Safety
+    ///
+    /// Same as [`cmd_next_subpass`](ash::Device::cmd_next_subpass).
+    pub unsafe fn next_subpass(&mut self, contents: vk::SubpassContents) {
+        self.device.cmd_next_subpass(self.command_buffer, contents);
+    }
+}
+
+impl<'s> Drop for RenderPassScope<'s> {
+    fn drop(&mut self) {
+        unsafe { self.device.cmd_end_render_pass(self.command_buffer) }
+    }
+}
+
+/// Dynamic rendering that ends when dropped
+///
+/// Created by [`CommandScope::begin_rendering`].
+pub struct RenderingScope<'s> {
+    device: &'s ash::Device,
+    command_buffer: vk::CommandBuffer,
+    _scope: PhantomData<&'s mut ()>,
+}
+
+impl<'s> Drop for RenderingScope<'s> {
+    fn drop(&mut self) {
+        unsafe { self.device.cmd_end_rendering(self.command_buffer) }
+    }
+}
+
+/// Query that ends when dropped
+///
+/// Created by [`CommandScope::begin_query`].
+pub struct QueryScope<'s> {
+    device: &'s ash::Device,
+    command_buffer: vk::CommandBuffer,
+    query_pool: vk::QueryPool,
+    query: u32,
+    _scope: PhantomData<&'s mut ()>,
+}
+
+impl<'s> Drop for QueryScope<'s> {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .cmd_end_query(self.command_buffer, self.query_pool, self.query)
+        }
+    }
+}
+
+/// Debug label region that ends when dropped
+///
+/// Created by [`CommandScope::begin_debug_label`].
+pub struct DebugLabelScope<'s> {
+    device: &'s ash::Device,
+    debug_utils: &'s ext::DebugUtils,
+    command_buffer: vk::CommandBuffer,
+    _scope: PhantomData<&'s mut ()>,
+}
+
+impl<'s> Drop for DebugLabelScope<'s> {
+    fn drop(&mut self) {
+        unsafe {
+            self.debug_utils
+                .cmd_end_debug_utils_label(self.command_buffer)
+        }
+    }
+}
+
+macro_rules! nested_scopes {
+    ($($Scope:ident,)*) => {
+        $(
+            impl<'s> CommandScope for $Scope<'s> {
+                fn device(&self) -> &ash::Device {
+                    self.device
+                }
+
+                fn command_buffer(&self) -> vk::CommandBuffer {
+                    self.command_buffer
+                }
+            }
+
+            impl<'s> fmt::Debug for $Scope<'s> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.debug_struct(stringify!($Scope))
+                        .field("command_buffer", &self.command_buffer)
+                        .finish_non_exhaustive()
+                }
+            }
+        )*
+    };
+}
+
+nested_scopes!(RenderPassScope, RenderingScope, QueryScope, DebugLabelScope,);
+
+#[cfg(test)]
+mod tests {
+    use ash::{extensions::ext, prelude::VkResult, vk};
+
+    use crate::{mock, CommandScope, DeviceExt, Recording};
+
+    #[test]
+    fn scopes_are_ended_innermost_first() {
+        let entry = mock::entry();
+        let (instance, device) = mock::instance_and_device();
+        let debug_utils = ext::DebugUtils::new(&entry, &instance);
+        let allocate_info = vk::CommandBufferAllocateInfo::builder().command_buffer_count(1);
+        let mut command_buffers =
+            unsafe { (&device).allocate_guarded_command_buffers(&allocate_info) }.unwrap();
+        let handle = command_buffers[0];
+
+        unsafe {
+            let mut recording =
+                Recording::begin(&mut command_buffers, 0, &Default::default()).unwrap();
+            {
+                let mut render_pass =
+                    recording.begin_render_pass(&Default::default(), vk::SubpassContents::INLINE);
+                render_pass.next_subpass(vk::SubpassContents::INLINE);
+                let mut query =
+                    render_pass.begin_query(vk::QueryPool::null(), 0, Default::default());
+                let _label = query.begin_debug_label(&debug_utils, &Default::default());
+            }
+            drop(recording.begin_rendering(&Default::default()));
+            recording.finish().unwrap();
+        }
+
+        let call = |function| mock::Call::new(function, handle, None);
+        assert_eq!(
+            mock::calls(&handle),
+            [
+                call("vkBeginCommandBuffer"),
+                call("vkCmdBeginRenderPass"),
+                call("vkCmdNextSubpass"),
+                call("vkCmdBeginQuery"),
+                call("vkCmdBeginDebugUtilsLabelEXT"),
+                call("vkCmdEndDebugUtilsLabelEXT"),
+                call("vkCmdEndQuery"),
+                call("vkCmdEndRenderPass"),
+                call("vkCmdBeginRendering"),
+                call("vkCmdEndRendering"),
+                call("vkEndCommandBuffer"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_are_ended_once_when_recording_bails_out() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocate_info = vk::CommandBufferAllocateInfo::builder().command_buffer_count(1);
+        let mut command_buffers =
+            unsafe { (&device).allocate_guarded_command_buffers(&allocate_info) }.unwrap();
+        let handle = command_buffers[0];
+
+        let record = |command_buffers| -> VkResult<()> {
+            unsafe {
+                let mut recording = Recording::begin(command_buffers, 0, &Default::default())?;
+                let _render_pass =
+                    recording.begin_render_pass(&Default::default(), vk::SubpassContents::INLINE);
+                Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+                unreachable!()
+            }
+        };
+        assert_eq!(
+            record(&mut command_buffers),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+        );
+
+        let call = |function| mock::Call::new(function, handle, None);
+        assert_eq!(
+            mock::calls(&handle),
+            [
+                call("vkBeginCommandBuffer"),
+                call("vkCmdBeginRenderPass"),
+                call("vkCmdEndRenderPass"),
+                call("vkEndCommandBuffer"),
+            ]
+        );
+    }
+}
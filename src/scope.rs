@@ -0,0 +1,370 @@
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ash::vk;
+
+use crate::destroy::drop_last_to_first;
+use crate::{Destroyable, GuardedResource};
+
+/// Arena of guarded resources that are destroyed together
+///
+/// [`Guarded`](crate::Guarded) works per-resource, which gets verbose when a function creates
+/// many resources that all need to be [`.take()`](GuardedResource::take)n at the end. A
+/// [`GuardScope`] holds any number of resources with arbitrary (possibly different) destroyers
+/// and hands out lightweight [`Scoped`] handles that can be used to index the scope. When the
+/// scope is dropped, its resources are destroyed in reverse order, unless
+/// [`commit`](Self::commit) was called:
+///
+/// ```
+/// # use ash::{extensions::khr, prelude::VkResult, vk};
+/// use ashpan::{DeviceExt, GuardScope, SwapchainExt};
+///
+/// struct Presentation {
+///     swapchain: vk::SwapchainKHR,
+///     render_pass: vk::RenderPass,
+///     framebuffers: Vec<vk::Framebuffer>,
+/// }
+///
+/// unsafe fn create_presentation(
+///     device: &ash::Device,
+///     swapchain_fn: &khr::Swapchain,
+/// ) -> VkResult<Presentation> {
+///     let mut scope = GuardScope::new();
+///     let swapchain = scope.adopt(swapchain_fn.create_guarded_swapchain(unimplemented!(), None)?);
+///     let render_pass = scope.adopt(device.create_guarded_render_pass(unimplemented!(), None)?);
+///     // If this fails, render_pass and then swapchain are destroyed
+///     let framebuffers = scope.adopt(create_framebuffers(device, scope[render_pass])?);
+///
+///     let presentation = Presentation {
+///         swapchain: scope[swapchain],
+///         render_pass: scope[render_pass],
+///         framebuffers: scope.take(framebuffers),
+///     };
+///     scope.commit();
+///     Ok(presentation)
+/// }
+/// # unsafe fn create_framebuffers(
+/// #     _: &ash::Device,
+/// #     _: vk::RenderPass,
+/// # ) -> VkResult<ashpan::Guarded<Vec<vk::Framebuffer>>> {
+/// #     unimplemented!()
+/// # }
+/// ```
+///
+/// Indexing with a handle from a different scope, or one whose resource was already
+/// [`take`](Self::take)n, panics.
+pub struct GuardScope<'s> {
+    id: u64,
+    // Taken entries are left as None so that indices remain valid.
+    entries: Vec<Option<Box<dyn Entry + 's>>>,
+}
+
+/// Handle to a resource held by a [`GuardScope`]
+pub struct Scoped<Resource> {
+    scope: u64,
+    index: usize,
+    _resource: PhantomData<fn() -> Resource>,
+}
+
+trait Entry {
+    fn resource(&self) -> &dyn Any;
+    fn resource_mut(&mut self) -> &mut dyn Any;
+    fn take(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<'s, Resource, Destroyer> Entry for GuardedResource<'s, Resource, Destroyer>
+where
+    Resource: Destroyable + 'static,
+    Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer>,
+{
+    fn resource(&self) -> &dyn Any {
+        &**self
+    }
+
+    fn resource_mut(&mut self) -> &mut dyn Any {
+        &mut **self
+    }
+
+    fn take(self: Box<Self>) -> Box<dyn Any> {
+        Box::new(GuardedResource::take(*self))
+    }
+}
+
+static NEXT_SCOPE_ID: AtomicU64 = AtomicU64::new(0);
+
+impl<'s> GuardScope<'s> {
+    /// Creates an empty [`GuardScope`].
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds `resource` to the scope. `destroyer` and `allocation_callbacks` are used during
+    /// destruction.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that it is safe to destroy `resource` when the [`GuardScope`] is dropped.
+    pub unsafe fn guard<Resource, Destroyer>(
+        &mut self,
+        resource: Resource,
+        destroyer: Destroyer,
+        allocation_callbacks: Option<&'s vk::AllocationCallbacks>,
+    ) -> Scoped<Resource>
+    where
+        Resource: Destroyable + 'static,
+        Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer> + 's,
+    {
+        self.adopt(GuardedResource::new(
+            resource,
+            destroyer,
+            allocation_callbacks,
+        ))
+    }
+
+    /// Moves an existing guard into the scope.
+    pub fn adopt<Resource, Destroyer>(
+        &mut self,
+        guarded: GuardedResource<'s, Resource, Destroyer>,
+    ) -> Scoped<Resource>
+    where
+        Resource: Destroyable + 'static,
+        Destroyer: Deref<Target = <Resource as Destroyable>::Destroyer> + 's,
+    {
+        self.entries.push(Some(Box::new(guarded)));
+        Scoped {
+            scope: self.id,
+            index: self.entries.len() - 1,
+            _resource: PhantomData,
+        }
+    }
+
+    /// Removes the resource referred to by `scoped` from the scope without destroying it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scoped` is from a different scope or was already taken.
+    pub fn take<Resource: 'static>(&mut self, scoped: Scoped<Resource>) -> Resource {
+        self.check(scoped);
+        let entry = self.entries[scoped.index]
+            .take()
+            .expect("resource was already taken from GuardScope");
+        *entry.take().downcast().unwrap()
+    }
+
+    /// Disarms the scope, so that none of its resources are destroyed.
+    pub fn commit(mut self) {
+        for entry in self.entries.drain(..).flatten() {
+            entry.take();
+        }
+    }
+
+    /// Returns the number of resources in the scope.
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    /// Returns `true` if the scope holds no resources.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn check<Resource>(&self, scoped: Scoped<Resource>) {
+        assert_eq!(
+            scoped.scope, self.id,
+            "Scoped handle used with a different GuardScope"
+        );
+    }
+
+    fn entry<Resource>(&self, scoped: Scoped<Resource>) -> &(dyn Entry + 's) {
+        self.check(scoped);
+        self.entries[scoped.index]
+            .as_deref()
+            .expect("resource was already taken from GuardScope")
+    }
+}
+
+impl<'s> Default for GuardScope<'s> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s> fmt::Debug for GuardScope<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardScope")
+            .field("id", &self.id)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<'s, Resource: 'static> Index<Scoped<Resource>> for GuardScope<'s> {
+    type Output = Resource;
+
+    fn index(&self, scoped: Scoped<Resource>) -> &Resource {
+        self.entry(scoped).resource().downcast_ref().unwrap()
+    }
+}
+
+impl<'s, Resource: 'static> IndexMut<Scoped<Resource>> for GuardScope<'s> {
+    fn index_mut(&mut self, scoped: Scoped<Resource>) -> &mut Resource {
+        self.check(scoped);
+        self.entries[scoped.index]
+            .as_deref_mut()
+            .expect("resource was already taken from GuardScope")
+            .resource_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+}
+
+impl<'s> Drop for GuardScope<'s> {
+    fn drop(&mut self) {
+        drop_last_to_first(&mut self.entries);
+    }
+}
+
+impl<Resource> Clone for Scoped<Resource> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Resource> Copy for Scoped<Resource> {}
+
+impl<Resource> PartialEq for Scoped<Resource> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.scope, self.index) == (other.scope, other.index)
+    }
+}
+
+impl<Resource> Eq for Scoped<Resource> {}
+
+impl<Resource> fmt::Debug for Scoped<Resource> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scoped")
+            .field("scope", &self.scope)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use ash::{extensions::khr, vk};
+
+    use crate::{mock, Destroyable, DeviceExt, GuardScope, Guarded, SwapchainExt};
+
+    #[test]
+    fn mixed_destroyers_are_destroyed_in_reverse() {
+        let (instance, device) = mock::instance_and_device();
+        let swapchain_fn = khr::Swapchain::new(&instance, &device);
+        let (buffer, swapchain);
+
+        {
+            let mut scope = GuardScope::new();
+            let scoped_buffer = scope.adopt(
+                unsafe { (&device).create_guarded_buffer(&Default::default(), None) }.unwrap(),
+            );
+            let scoped_swapchain = scope.adopt(
+                unsafe { (&swapchain_fn).create_guarded_swapchain(&Default::default(), None) }
+                    .unwrap(),
+            );
+            assert_eq!(scope.len(), 2);
+            buffer = scope[scoped_buffer];
+            swapchain = scope[scoped_swapchain];
+        }
+
+        assert_eq!(
+            mock::calls(&device)[3..],
+            [
+                mock::Call::new("vkDestroySwapchainKHR", swapchain, None),
+                mock::Call::new("vkDestroyBuffer", buffer, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn remaining_resources_are_destroyed_in_reverse_when_one_panics() {
+        struct Panicking;
+
+        impl Destroyable for Panicking {
+            type Destroyer = ();
+
+            unsafe fn destroy_with(&mut self, _: &(), _: Option<&vk::AllocationCallbacks>) {
+                panic!("failed to destroy");
+            }
+        }
+
+        let (_instance, device) = mock::instance_and_device();
+        let mut scope = GuardScope::new();
+        let buffer = scope
+            .adopt(unsafe { (&device).create_guarded_buffer(&Default::default(), None) }.unwrap());
+        scope.adopt(unsafe { Guarded::new(Panicking, &(), None) });
+        let semaphore = scope.adopt(
+            unsafe { (&device).create_guarded_semaphore(&Default::default(), None) }.unwrap(),
+        );
+        let (buffer, semaphore) = (scope[buffer], scope[semaphore]);
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(scope))).is_err());
+        assert_eq!(
+            mock::calls(&device)[3..],
+            [
+                mock::Call::new("vkDestroySemaphore", semaphore, None),
+                mock::Call::new("vkDestroyBuffer", buffer, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn committed_and_taken_resources_are_not_destroyed() {
+        let (_instance, device) = mock::instance_and_device();
+        let allocation_callbacks = vk::AllocationCallbacks::default();
+        let mut scope = GuardScope::new();
+
+        let _semaphore = scope.adopt(
+            unsafe {
+                (&device).create_guarded_semaphore(&Default::default(), Some(&allocation_callbacks))
+            }
+            .unwrap(),
+        );
+        let fences = scope.adopt(
+            unsafe {
+                Guarded::try_new_from(
+                    [device.create_fence(&Default::default(), None)],
+                    &device,
+                    None,
+                )
+            }
+            .unwrap(),
+        );
+        let fences = scope.take(fences);
+        assert_eq!(fences.len(), 1);
+        assert_eq!(scope.len(), 1);
+        scope.commit();
+
+        let calls = mock::calls(&device);
+        assert_eq!(calls.len(), 3, "unexpected calls: {calls:?}");
+        assert!(calls
+            .iter()
+            .all(|call| !call.function.starts_with("vkDestroy")));
+    }
+
+    #[test]
+    #[should_panic = "different GuardScope"]
+    fn handles_from_other_scopes_are_rejected() {
+        let (_instance, device) = mock::instance_and_device();
+        let mut scope = GuardScope::new();
+        let other_scope = GuardScope::new();
+        let semaphore = unsafe { scope.guard(vk::Semaphore::null(), &device, None) };
+        let _ = other_scope[semaphore];
+    }
+}
@@ -0,0 +1,251 @@
+use std::ops::Deref;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::{DeletionQueue, DeviceExt, Recording};
+
+impl<'a, DeviceRef> DeletionQueue<'a, DeviceRef>
+where
+    DeviceRef: Clone + Deref<Target = ash::Device> + 'a,
+{
+    /// Records a one-shot command buffer with `record`, submits it to `queue` and waits up to
+    /// `timeout` nanoseconds for it to complete
+    ///
+    /// A transient command pool for `queue_family_index`, a primary command buffer allocated from
+    /// it, and a fence are created for the submission. They're destroyed before returning unless
+    /// waiting fails with anything but [`vk::Result::ERROR_DEVICE_LOST`] (e.g. times out), in
+    /// which case the GPU may still be using them, so they're queued to be destroyed by
+    /// [`collect`](Self::collect) once the fence signals. Returns what `record` returned:
+    ///
+    /// ```
+    /// # use ash::{prelude::VkResult, vk};
+    /// use ashpan::{CommandScope, DeletionQueue};
+    ///
+    /// unsafe fn upload<'d>(
+    ///     deletion_queue: &mut DeletionQueue<'d, &'d ash::Device>,
+    ///     queue: vk::Queue,
+    ///     queue_family_index: u32,
+    ///     staging_buffer: vk::Buffer,
+    ///     buffer: vk::Buffer,
+    ///     region: vk::BufferCopy,
+    /// ) -> VkResult<()> {
+    ///     deletion_queue.submit_one_shot(queue, queue_family_index, 1_000_000_000, |recording| {
+    ///         let (device, command_buffer) = (recording.device(), recording.command_buffer());
+    ///         device.cmd_copy_buffer(command_buffer, staging_buffer, buffer, &[region]);
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// The value returned by `record` is dropped if submitting or waiting fails, so it shouldn't
+    /// own resources that the commands use. If the objects are queued, resources that the commands
+    /// use may still be in use and must outlive the queued fence as well.
+    ///
+    /// # Safety
+    ///
+    /// `queue` must belong to the queue's device and to `queue_family_index`, and must be
+    /// externally synchronized. The commands recorded by `record` must be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from creating the objects, `record`, ending the command buffer,
+    /// [`queue_submit`](ash::Device::queue_submit) or
+    /// [`wait_for_fences`](ash::Device::wait_for_fences), including [`vk::Result::TIMEOUT`] and
+    /// [`vk::Result::ERROR_DEVICE_LOST`].
+    pub unsafe fn submit_one_shot<T>(
+        &mut self,
+        queue: vk::Queue,
+        queue_family_index: u32,
+        timeout: u64,
+        record: impl FnOnce(&mut Recording<'_>) -> VkResult<T>,
+    ) -> VkResult<T> {
+        let device = self.device().clone();
+        let command_pool_info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family_index);
+        let command_pool = device.create_guarded_command_pool(&command_pool_info, None)?;
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let mut command_buffers = device.allocate_guarded_command_buffers(&allocate_info)?;
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        let mut recording = Recording::begin(&mut command_buffers, 0, &begin_info)?;
+        let value = record(&mut recording)?;
+        recording.finish()?;
+
+        let fence = device.create_guarded_fence(&Default::default(), None)?;
+        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+        device.queue_submit(queue, &[submit_info.build()], *fence)?;
+
+        match device.wait_for_fences(&[*fence], true, timeout) {
+            Ok(()) => Ok(value),
+            // The device no longer executes anything after DEVICE_LOST, so destroying is fine.
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(vk::Result::ERROR_DEVICE_LOST),
+            // Otherwise (e.g. TIMEOUT or running out of memory while waiting), the submission may
+            // still be executing.
+            Err(err) => {
+                let fence_handle = *fence;
+                command_pool.defer_until(self, fence_handle);
+                command_buffers.defer_until(self, fence_handle);
+                fence.defer_until(self, fence_handle);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::{self, Handle};
+
+    use crate::{mock, CommandScope, DeletionQueue};
+
+    #[test]
+    fn objects_are_destroyed_once_the_submission_completes() {
+        let (_instance, device) = mock::instance_and_device();
+        let queue = mock::queue();
+        let mut deletion_queue = DeletionQueue::new(&device);
+        let mut command_buffer = vk::CommandBuffer::null();
+
+        let value = unsafe {
+            deletion_queue.submit_one_shot(queue, 0, 1000, |recording| {
+                command_buffer = recording.command_buffer();
+                Ok(42)
+            })
+        };
+        assert_eq!(value, Ok(42));
+        assert!(deletion_queue.is_empty());
+
+        let calls = mock::calls(&device);
+        let command_pool = vk::CommandPool::from_raw(calls[1].handle);
+        let fence = vk::Fence::from_raw(calls[3].handle);
+        assert_eq!(
+            calls[1..],
+            [
+                mock::Call::new("vkCreateCommandPool", command_pool, None),
+                mock::Call::pooled("vkAllocateCommandBuffers", command_buffer, command_pool),
+                mock::Call::new("vkCreateFence", fence, None),
+                mock::Call::new("vkWaitForFences", fence, None),
+                mock::Call::new("vkDestroyFence", fence, None),
+                mock::Call::pooled("vkFreeCommandBuffers", command_buffer, command_pool),
+                mock::Call::new("vkDestroyCommandPool", command_pool, None),
+            ]
+        );
+        assert_eq!(
+            mock::calls(&queue),
+            [mock::Call::new("vkQueueSubmit", fence, None)]
+        );
+        assert_eq!(
+            mock::calls(&command_buffer),
+            [
+                mock::Call::new("vkBeginCommandBuffer", command_buffer, None),
+                mock::Call::new("vkEndCommandBuffer", command_buffer, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn objects_are_deferred_until_the_fence_signals_after_a_timeout() {
+        let (_instance, device) = mock::instance_and_device();
+        let queue = mock::queue();
+        mock::stall_queue(queue);
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let result = unsafe { deletion_queue.submit_one_shot(queue, 0, 1000, |_| Ok(())) };
+        assert_eq!(result, Err(vk::Result::TIMEOUT));
+        assert_eq!(deletion_queue.len(), 3);
+        assert_eq!(mock::calls(&device).len(), 5);
+
+        let fence = vk::Fence::from_raw(mock::calls(&device)[3].handle);
+        deletion_queue.collect().unwrap();
+        assert_eq!(deletion_queue.len(), 3);
+        mock::signal_fence(fence);
+        deletion_queue.collect().unwrap();
+        assert!(deletion_queue.is_empty());
+
+        let functions: Vec<_> = mock::calls(&device)[5..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "vkDestroyFence",
+                "vkFreeCommandBuffers",
+                "vkDestroyCommandPool"
+            ]
+        );
+    }
+
+    #[test]
+    fn objects_are_deferred_when_waiting_runs_out_of_memory() {
+        let (_instance, device) = mock::instance_and_device();
+        let queue = mock::queue();
+        mock::stall_queue(queue);
+        mock::fail_fence_waits(&device, vk::Result::ERROR_OUT_OF_HOST_MEMORY);
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let result = unsafe { deletion_queue.submit_one_shot(queue, 0, 1000, |_| Ok(())) };
+        assert_eq!(result, Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY));
+        assert_eq!(deletion_queue.len(), 3);
+        assert_eq!(mock::calls(&device).len(), 5);
+    }
+
+    #[test]
+    fn objects_are_destroyed_immediately_after_device_loss() {
+        let (_instance, device) = mock::instance_and_device();
+        let queue = mock::queue();
+        mock::stall_queue(queue);
+        mock::lose_device(&device);
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let result = unsafe { deletion_queue.submit_one_shot(queue, 0, 1000, |_| Ok(())) };
+        assert_eq!(result, Err(vk::Result::ERROR_DEVICE_LOST));
+        assert!(deletion_queue.is_empty());
+
+        let functions: Vec<_> = mock::calls(&device)[5..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "vkDestroyFence",
+                "vkFreeCommandBuffers",
+                "vkDestroyCommandPool"
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_is_submitted_when_recording_fails() {
+        let (_instance, device) = mock::instance_and_device();
+        let queue = mock::queue();
+        let mut deletion_queue = DeletionQueue::new(&device);
+
+        let result = unsafe {
+            deletion_queue.submit_one_shot(queue, 0, 1000, |_| -> ash::prelude::VkResult<()> {
+                Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+            })
+        };
+        assert_eq!(result, Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY));
+        assert!(mock::calls(&queue).is_empty());
+
+        let functions: Vec<_> = mock::calls(&device)[1..]
+            .iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "vkCreateCommandPool",
+                "vkAllocateCommandBuffers",
+                "vkFreeCommandBuffers",
+                "vkDestroyCommandPool",
+            ]
+        );
+    }
+}
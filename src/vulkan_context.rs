@@ -0,0 +1,308 @@
+use std::fmt;
+use std::ops::Deref;
+
+use ash::{
+    extensions::{ext, khr},
+    vk,
+};
+
+use crate::{Destroyable, GuardedResource};
+
+type OwnedGuarded<Resource> =
+    GuardedResource<'static, Resource, Box<<Resource as Destroyable>::Destroyer>>;
+
+/// Owns everything from the [`ash::Entry`] to the [`ash::Device`], tearing it down in order
+///
+/// Guards returned by
+/// [`EntryExt::create_guarded_instance`](crate::EntryExt::create_guarded_instance) and
+/// [`InstanceExt::create_guarded_device`](crate::InstanceExt::create_guarded_device) don't borrow
+/// each other, so nothing stops the instance from being destroyed before the device.
+/// [`VulkanContext`] takes ownership of those guards (along with an optional debug messenger and
+/// surface) and, when dropped, waits for the device to be idle and then destroys:
+///
+/// 1. the device,
+/// 2. the surface,
+/// 3. the debug messenger,
+/// 4. the instance,
+///
+/// before finally dropping the entry.
+///
+/// ```
+/// # use ash::{extensions::{ext, khr}, prelude::VkResult, vk};
+/// use ashpan::{DebugUtilsExt, EntryExt, InstanceExt, SurfaceExt, VulkanContext};
+///
+/// unsafe fn create_context(entry: ash::Entry) -> VkResult<VulkanContext> {
+///     let instance = entry.create_guarded_instance(unimplemented!(), None)?;
+///     let debug_utils = ext::DebugUtils::new(&entry, &instance);
+///     let messenger = (&debug_utils).create_guarded_debug_utils_messenger(unimplemented!(), None)?;
+///     let surface_fn = khr::Surface::new(&entry, &instance);
+///     let surface = (&surface_fn).create_guarded_headless_surface(
+///         &ext::HeadlessSurface::new(&entry, &instance),
+///         unimplemented!(),
+///         None,
+///     )?;
+///     let physical_device = unimplemented!();
+///     let device = instance.create_guarded_device(physical_device, unimplemented!(), None)?;
+///
+///     Ok(VulkanContext::builder(entry, instance)
+///         .debug_utils_messenger(messenger)
+///         .surface(surface)
+///         .build(physical_device, device))
+/// }
+/// ```
+pub struct VulkanContext {
+    // Fields are dropped in declaration order, after Drop::drop() waits for the device.
+    device: GuardedResource<'static, ash::Device, &'static ()>,
+    physical_device: vk::PhysicalDevice,
+    surface: Option<OwnedGuarded<vk::SurfaceKHR>>,
+    debug_utils_messenger: Option<OwnedGuarded<vk::DebugUtilsMessengerEXT>>,
+    instance: GuardedResource<'static, ash::Instance, &'static ()>,
+    entry: ash::Entry,
+}
+
+/// Builder for [`VulkanContext`]
+///
+/// If the builder is dropped without being built, its resources are destroyed in the same order
+/// as a [`VulkanContext`]'s.
+pub struct VulkanContextBuilder {
+    // Fields are dropped in declaration order.
+    surface: Option<OwnedGuarded<vk::SurfaceKHR>>,
+    debug_utils_messenger: Option<OwnedGuarded<vk::DebugUtilsMessengerEXT>>,
+    instance: GuardedResource<'static, ash::Instance, &'static ()>,
+    entry: ash::Entry,
+}
+
+/// Moves `guarded`'s destroyer into a [`Box`], so that the guard's type doesn't depend on how the
+/// destroyer was referred to.
+fn owned<Resource, Destroyer>(
+    guarded: GuardedResource<'static, Resource, Destroyer>,
+) -> OwnedGuarded<Resource>
+where
+    Resource: Destroyable,
+    Resource::Destroyer: Clone,
+    Destroyer: Deref<Target = Resource::Destroyer> + Clone,
+{
+    let destroyer = Box::new((*guarded.destroyer()).clone());
+    let allocation_callbacks = guarded.allocation_callbacks();
+    // The resource is re-guarded with an equivalent destroyer.
+    unsafe { GuardedResource::new(guarded.take(), destroyer, allocation_callbacks) }
+}
+
+impl VulkanContext {
+    /// Starts building a [`VulkanContext`] that owns `entry` and `instance`, which must have been
+    /// created by `entry`.
+    pub fn builder(
+        entry: ash::Entry,
+        instance: GuardedResource<'static, ash::Instance, &'static ()>,
+    ) -> VulkanContextBuilder {
+        VulkanContextBuilder {
+            surface: None,
+            debug_utils_messenger: None,
+            instance,
+            entry,
+        }
+    }
+
+    /// Returns the entry.
+    pub fn entry(&self) -> &ash::Entry {
+        &self.entry
+    }
+
+    /// Returns the instance.
+    pub fn instance(&self) -> &ash::Instance {
+        &self.instance
+    }
+
+    /// Returns the physical device that the device was created from.
+    pub fn physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+
+    /// Returns the device.
+    pub fn device(&self) -> &ash::Device {
+        &self.device
+    }
+
+    /// Returns the [`ext::DebugUtils`] loader that destroys the debug messenger, if any.
+    pub fn debug_utils(&self) -> Option<&ext::DebugUtils> {
+        self.debug_utils_messenger
+            .as_ref()
+            .map(|messenger| &**messenger.destroyer_ref())
+    }
+
+    /// Returns the debug messenger, if any.
+    pub fn debug_utils_messenger(&self) -> Option<vk::DebugUtilsMessengerEXT> {
+        self.debug_utils_messenger.as_deref().copied()
+    }
+
+    /// Returns the [`khr::Surface`] loader that destroys the surface, if any.
+    pub fn surface_fn(&self) -> Option<&khr::Surface> {
+        self.surface
+            .as_ref()
+            .map(|surface| &**surface.destroyer_ref())
+    }
+
+    /// Returns the surface, if any.
+    pub fn surface(&self) -> Option<vk::SurfaceKHR> {
+        self.surface.as_deref().copied()
+    }
+}
+
+impl Drop for VulkanContext {
+    fn drop(&mut self) {
+        // Nothing useful can be done if waiting fails (e.g. because the device was lost), and
+        // the device still needs to be destroyed.
+        let _ = unsafe { self.device.device_wait_idle() };
+    }
+}
+
+impl fmt::Debug for VulkanContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VulkanContext")
+            .field("instance", &self.instance.handle())
+            .field("debug_utils_messenger", &self.debug_utils_messenger())
+            .field("surface", &self.surface())
+            .field("physical_device", &self.physical_device)
+            .field("device", &self.device.handle())
+            .finish()
+    }
+}
+
+impl VulkanContextBuilder {
+    /// Adds a debug messenger, which must have been created with the builder's instance.
+    pub fn debug_utils_messenger<Destroyer>(
+        mut self,
+        debug_utils_messenger: GuardedResource<'static, vk::DebugUtilsMessengerEXT, Destroyer>,
+    ) -> Self
+    where
+        Destroyer: Deref<Target = ext::DebugUtils> + Clone,
+    {
+        self.debug_utils_messenger = Some(owned(debug_utils_messenger));
+        self
+    }
+
+    /// Adds a surface, which must have been created with the builder's instance.
+    pub fn surface<Destroyer>(
+        mut self,
+        surface: GuardedResource<'static, vk::SurfaceKHR, Destroyer>,
+    ) -> Self
+    where
+        Destroyer: Deref<Target = khr::Surface> + Clone,
+    {
+        self.surface = Some(owned(surface));
+        self
+    }
+
+    /// Finishes building the [`VulkanContext`] with `device`, which must have been created from
+    /// `physical_device` of the builder's instance.
+    pub fn build(
+        self,
+        physical_device: vk::PhysicalDevice,
+        device: GuardedResource<'static, ash::Device, &'static ()>,
+    ) -> VulkanContext {
+        VulkanContext {
+            device,
+            physical_device,
+            surface: self.surface,
+            debug_utils_messenger: self.debug_utils_messenger,
+            instance: self.instance,
+            entry: self.entry,
+        }
+    }
+}
+
+impl fmt::Debug for VulkanContextBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VulkanContextBuilder")
+            .field("instance", &self.instance.handle())
+            .field(
+                "debug_utils_messenger",
+                &self.debug_utils_messenger.as_deref(),
+            )
+            .field("surface", &self.surface.as_deref())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::{
+        extensions::{ext, khr},
+        vk,
+    };
+
+    use crate::{mock, DebugUtilsExt, EntryExt, InstanceExt, SurfaceExt, VulkanContext};
+
+    #[test]
+    fn resources_are_destroyed_in_order() {
+        let entry = mock::entry();
+        let (instance, messenger, surface, device);
+
+        unsafe {
+            let guarded_instance = entry
+                .create_guarded_instance(&Default::default(), None)
+                .unwrap();
+            let debug_utils = ext::DebugUtils::new(&entry, &guarded_instance);
+            let guarded_messenger = (&debug_utils)
+                .create_guarded_debug_utils_messenger(&Default::default(), None)
+                .unwrap();
+            let surface_fn = khr::Surface::new(&entry, &guarded_instance);
+            let guarded_surface = (&surface_fn)
+                .create_guarded_headless_surface(
+                    &ext::HeadlessSurface::new(&entry, &guarded_instance),
+                    &Default::default(),
+                    None,
+                )
+                .unwrap();
+            let guarded_device = guarded_instance
+                .create_guarded_device(vk::PhysicalDevice::null(), &Default::default(), None)
+                .unwrap();
+
+            let context = VulkanContext::builder(entry, guarded_instance)
+                .debug_utils_messenger(guarded_messenger)
+                .surface(guarded_surface)
+                .build(vk::PhysicalDevice::null(), guarded_device);
+            assert!(context.debug_utils().is_some());
+            assert!(context.surface_fn().is_some());
+
+            instance = context.instance().handle();
+            messenger = context.debug_utils_messenger().unwrap();
+            surface = context.surface().unwrap();
+            device = context.device().handle();
+        }
+
+        assert_eq!(
+            mock::calls(&device),
+            [
+                mock::Call::new("vkCreateDevice", device, None),
+                mock::Call::new("vkDeviceWaitIdle", device, None),
+                mock::Call::new("vkDestroyDevice", device, None),
+            ]
+        );
+        assert_eq!(
+            mock::calls(&instance)[3..],
+            [
+                mock::Call::new("vkDestroySurfaceKHR", surface, None),
+                mock::Call::new("vkDestroyDebugUtilsMessengerEXT", messenger, None),
+                mock::Call::new("vkDestroyInstance", instance, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unbuilt_builder_destroys_its_resources() {
+        let entry = mock::entry();
+        let instance = unsafe { entry.create_guarded_instance(&Default::default(), None) }.unwrap();
+        let handle = instance.handle();
+
+        drop(VulkanContext::builder(entry, instance));
+
+        assert_eq!(
+            mock::calls(&handle),
+            [
+                mock::Call::new("vkCreateInstance", handle, None),
+                mock::Call::new("vkDestroyInstance", handle, None),
+            ]
+        );
+    }
+}
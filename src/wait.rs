@@ -0,0 +1,200 @@
+use std::ops::{Deref, DerefMut};
+
+use ash::vk;
+
+use crate::Destroyable;
+
+/// [`ash::Device`] that waits for itself to be idle before being destroyed
+///
+/// Destroying a device while any of its queues are still executing work is undefined behavior,
+/// which is easy to run into when an error unwinds out of the middle of a frame. Guarding a
+/// [`WaitIdle`] instead of a bare [`ash::Device`] calls
+/// [`device_wait_idle`](ash::Device::device_wait_idle) first:
+///
+/// ```
+/// # use ash::{prelude::VkResult, vk};
+/// use ashpan::{Guarded, InstanceExt};
+///
+/// unsafe fn create_device(
+///     instance: &ash::Instance,
+///     physical_device: vk::PhysicalDevice,
+/// ) -> VkResult<Guarded<'static, ashpan::WaitIdle>> {
+///     let create_info = unimplemented!();
+///     let device = instance.create_guarded_device(physical_device, create_info, None)?;
+///     Ok(device.wait_idle_on_drop())
+/// }
+/// ```
+///
+/// If waiting fails (e.g. because the device was lost), the device is destroyed anyway.
+#[derive(Clone)]
+pub struct WaitIdle(ash::Device);
+
+impl WaitIdle {
+    /// Wraps `device`, to be waited on until it's idle before being destroyed.
+    pub fn new(device: ash::Device) -> Self {
+        Self(device)
+    }
+
+    /// Returns the wrapped device.
+    pub fn into_inner(self) -> ash::Device {
+        self.0
+    }
+}
+
+impl Deref for WaitIdle {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &ash::Device {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for WaitIdle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WaitIdle").field(&self.0.handle()).finish()
+    }
+}
+
+impl Destroyable for WaitIdle {
+    type Destroyer = ();
+
+    unsafe fn destroy_with(
+        &mut self,
+        destroyer: &(),
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        let _ = self.0.device_wait_idle();
+        self.0.destroy_with(destroyer, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        self.0.visit_handles(visitor);
+    }
+}
+
+/// Fences that are waited on before being destroyed
+///
+/// Like [`WaitIdle`], but for a container of [`vk::Fence`]s such as `Vec<vk::Fence>`. When
+/// destroyed, waits up to `timeout` nanoseconds for all of the fences to signal (as with
+/// [`wait_for_fences`](ash::Device::wait_for_fences)) before destroying them. If waiting times out
+/// or fails, the fences are destroyed anyway.
+///
+/// Usually created via
+/// [`GuardedResource::wait_for_fences_on_drop`](crate::GuardedResource::wait_for_fences_on_drop).
+#[derive(Clone, Debug)]
+pub struct WaitForFences<Fences> {
+    fences: Fences,
+    timeout: u64,
+}
+
+impl<Fences> WaitForFences<Fences>
+where
+    Fences: AsRef<[vk::Fence]> + Destroyable<Destroyer = ash::Device>,
+{
+    /// Wraps `fences`, to be waited on for up to `timeout` nanoseconds before being destroyed.
+    pub fn new(fences: Fences, timeout: u64) -> Self {
+        Self { fences, timeout }
+    }
+
+    /// Returns the timeout in nanoseconds.
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    /// Returns the wrapped fences.
+    pub fn into_inner(self) -> Fences {
+        self.fences
+    }
+}
+
+impl<Fences> Deref for WaitForFences<Fences> {
+    type Target = Fences;
+
+    fn deref(&self) -> &Fences {
+        &self.fences
+    }
+}
+
+impl<Fences> DerefMut for WaitForFences<Fences> {
+    fn deref_mut(&mut self) -> &mut Fences {
+        &mut self.fences
+    }
+}
+
+impl<Fences> Destroyable for WaitForFences<Fences>
+where
+    Fences: AsRef<[vk::Fence]> + Destroyable<Destroyer = ash::Device>,
+{
+    type Destroyer = ash::Device;
+
+    unsafe fn destroy_with(
+        &mut self,
+        device: &ash::Device,
+        allocation_callbacks: Option<&vk::AllocationCallbacks>,
+    ) {
+        let fences = self.fences.as_ref();
+        if !fences.is_empty() {
+            let _ = device.wait_for_fences(fences, true, self.timeout);
+        }
+        self.fences.destroy_with(device, allocation_callbacks);
+    }
+
+    fn visit_handles(&self, visitor: &mut dyn FnMut(vk::ObjectType, u64)) {
+        self.fences.visit_handles(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::{mock, Guarded, InstanceExt};
+
+    #[test]
+    fn device_waits_for_idle_before_destruction() {
+        let (instance, _device) = mock::instance_and_device();
+        let device = unsafe {
+            instance.create_guarded_device(vk::PhysicalDevice::null(), &Default::default(), None)
+        }
+        .unwrap()
+        .wait_idle_on_drop();
+        let handle = device.handle();
+        drop(device);
+
+        assert_eq!(
+            mock::calls(&handle),
+            [
+                mock::Call::new("vkCreateDevice", handle, None),
+                mock::Call::new("vkDeviceWaitIdle", handle, None),
+                mock::Call::new("vkDestroyDevice", handle, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn fences_are_waited_on_before_destruction() {
+        let (_instance, device) = mock::instance_and_device();
+        let fences = unsafe {
+            Guarded::try_new_from(
+                (0..2).map(|_| device.create_fence(&Default::default(), None)),
+                &device,
+                None,
+            )
+        }
+        .unwrap()
+        .wait_for_fences_on_drop(1_000_000);
+        assert_eq!(fences.timeout(), 1_000_000);
+        let handles = (**fences).clone();
+        drop(fences);
+
+        assert_eq!(
+            mock::calls(&device)[3..],
+            [
+                mock::Call::new("vkWaitForFences", handles[0], None),
+                mock::Call::new("vkWaitForFences", handles[1], None),
+                mock::Call::new("vkDestroyFence", handles[0], None),
+                mock::Call::new("vkDestroyFence", handles[1], None),
+            ]
+        );
+    }
+}